@@ -0,0 +1,94 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+use fsm::dfa::Dfa;
+
+/// The previous `Dfa::run`, which looked up every character in the alphabet `BiMap` (skipping
+/// the lookup when a character repeats), kept as the baseline for the dense lookup table.
+fn run_bimap(dfa: &Dfa, input: &str) -> bool {
+    let mut current_state = dfa.start_state_idx;
+    let mut prev: Option<(char, usize)> = None;
+
+    for c in input.chars() {
+        let class_idx = match prev {
+            Some((prev_char, prev_class)) if prev_char == c => prev_class,
+            _ => match dfa.alphabet.get_by_left(&c) {
+                Some(&alpha_idx) => {
+                    let class_idx = dfa.char_classes[alpha_idx];
+                    prev = Some((c, class_idx));
+                    class_idx
+                }
+                None => return false,
+            },
+        };
+        current_state = dfa.class_table[(current_state * dfa.class_count) + class_idx];
+        if dfa.trap_states[current_state] {
+            return false;
+        }
+    }
+
+    dfa.accept_states[current_state]
+}
+
+fn load(path: &str) -> Dfa {
+    let yaml = std::fs::read_to_string(path).expect("example spec exists");
+    fsm::parser::from_yaml(&yaml)
+        .expect("example spec is valid")
+        .dfa()
+        .clone()
+}
+
+fn bench_run(c: &mut Criterion) {
+    const LEN: usize = 1 << 20;
+
+    let decimal = load("examples/decimal.yml");
+    let digits: String = "1234567890".chars().cycle().take(LEN).collect();
+
+    let comment = load("examples/comment.yml");
+    let body: String = "The quick brown fox 42 "
+        .chars()
+        .cycle()
+        .take(LEN)
+        .collect();
+    let comment_text = format!("/*{}*/", body);
+
+    // non-Latin-1 characters go through the fallback map
+    let greek = fsm::regex::from_regex("[α-ω ]*")
+        .expect("pattern is valid")
+        .dfa()
+        .clone();
+    let greek_text: String = "αβγ δεζ ηθι κλμ ".chars().cycle().take(LEN).collect();
+
+    let cases = [
+        ("decimal", &decimal, &digits),
+        ("comment", &comment, &comment_text),
+        ("greek", &greek, &greek_text),
+    ];
+
+    let mut group = c.benchmark_group("run");
+    for (name, dfa, input) in cases {
+        let byte_dfa = dfa.to_byte_dfa();
+        assert!(dfa.run(input.chars()) && run_bimap(dfa, input));
+        assert!(byte_dfa.run(input.as_bytes()));
+        group.throughput(Throughput::Bytes(input.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("bimap", name), input, |b, input| {
+            b.iter(|| run_bimap(dfa, black_box(input)))
+        });
+        group.bench_with_input(BenchmarkId::new("run", name), input, |b, input| {
+            b.iter(|| dfa.run(black_box(input).chars()))
+        });
+        if input.is_ascii() {
+            group.bench_with_input(BenchmarkId::new("run_bytes", name), input, |b, input| {
+                b.iter(|| dfa.run_bytes(black_box(input).as_bytes()))
+            });
+        }
+        group.bench_with_input(BenchmarkId::new("utf8", name), input, |b, input| {
+            b.iter(|| byte_dfa.run(black_box(input).as_bytes()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_run);
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::dfa::Dfa;
+
+/// A summary of the structure of a DFA and of the language it accepts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageReport {
+    pub state_count: usize,
+    pub transition_count: usize,
+    pub alphabet_size: usize,
+    /// Number of equivalence classes of symbols that behave identically in every state.
+    pub class_count: usize,
+    /// Number of states reachable from the start state.
+    pub reachable_count: usize,
+    /// Number of states from which an accepting state can be reached.
+    pub coreachable_count: usize,
+    /// The machine accepts no strings at all.
+    pub is_empty: bool,
+    /// The machine accepts every string over its alphabet.
+    pub is_universal: bool,
+    /// The machine accepts only finitely many strings.
+    pub is_finite: bool,
+}
+
+/// Returns, for each state, whether a state marked in `targets` can be reached from it, where
+/// `successors` lists the destinations of the transitions leaving a state.
+pub(crate) fn coreachable<F, I>(targets: &[bool], successors: F) -> Vec<bool>
+where
+    F: Fn(usize) -> I,
+    I: IntoIterator<Item = usize>,
+{
+    let state_count = targets.len();
+
+    // reverse adjacency: dest -> sources
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); state_count];
+    for src in 0..state_count {
+        for dest in successors(src) {
+            // transitions of one source are added together, so this skips repeated edges
+            if predecessors[dest].last() != Some(&src) {
+                predecessors[dest].push(src);
+            }
+        }
+    }
+
+    let mut coreachable = targets.to_vec();
+    let mut queue: VecDeque<usize> = (0..state_count).filter(|&s| coreachable[s]).collect();
+    while let Some(state) = queue.pop_front() {
+        for &src in &predecessors[state] {
+            if !coreachable[src] {
+                coreachable[src] = true;
+                queue.push_back(src);
+            }
+        }
+    }
+
+    coreachable
+}
+
+impl Dfa {
+    /// Returns, for each state, whether it can be reached from the start state.
+    pub fn reachable_states(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.state_keys.len()];
+        for state in self.reachable_in_bfs_order() {
+            reachable[state] = true;
+        }
+        reachable
+    }
+
+    /// Returns, for each state, whether some accepting state can be reached from it.
+    pub fn coreachable_states(&self) -> Vec<bool> {
+        coreachable(&self.accept_states, |src| {
+            (0..self.class_count).map(move |class_idx| self.class_transition(src, class_idx))
+        })
+    }
+
+    /// Returns true if the DFA accepts no strings.
+    pub fn is_empty(&self) -> bool {
+        !self
+            .reachable_in_bfs_order()
+            .into_iter()
+            .any(|s| self.accept_states[s])
+    }
+
+    /// Returns true if the DFA accepts every string over its alphabet.
+    pub fn is_universal(&self) -> bool {
+        self.reachable_in_bfs_order()
+            .into_iter()
+            .all(|s| self.accept_states[s])
+    }
+
+    /// Returns true if the DFA accepts only finitely many strings.
+    ///
+    /// The language is infinite exactly when a cycle passes through a state that is both
+    /// reachable from the start state and co-reachable to an accepting state.
+    pub fn is_finite(&self) -> bool {
+        let reachable = self.reachable_states();
+        let coreachable = self.coreachable_states();
+        let useful: Vec<bool> = reachable
+            .iter()
+            .zip(&coreachable)
+            .map(|(&r, &c)| r && c)
+            .collect();
+
+        !self.has_cycle_within(&useful)
+    }
+
+    /// Computes a full report of the DFA's structure and language properties.
+    pub fn analyze(&self) -> LanguageReport {
+        let reachable = self.reachable_states();
+        let coreachable = self.coreachable_states();
+
+        LanguageReport {
+            state_count: self.state_keys.len(),
+            transition_count: self.state_keys.len() * self.alphabet.len(),
+            alphabet_size: self.alphabet.len(),
+            class_count: self.class_count,
+            reachable_count: reachable.iter().filter(|&&r| r).count(),
+            coreachable_count: coreachable.iter().filter(|&&c| c).count(),
+            is_empty: self.is_empty(),
+            is_universal: self.is_universal(),
+            is_finite: self.is_finite(),
+        }
+    }
+
+    /// Detects a cycle in the transition graph restricted to the states marked in `included`.
+    fn has_cycle_within(&self, included: &[bool]) -> bool {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Unvisited,
+            OnStack,
+            Done,
+        }
+
+        let mut marks = vec![Mark::Unvisited; self.state_keys.len()];
+
+        for root in (0..self.state_keys.len()).filter(|&s| included[s]) {
+            if marks[root] != Mark::Unvisited {
+                continue;
+            }
+
+            // iterative DFS: (state, next symbol class to explore)
+            let mut stack = vec![(root, 0)];
+            marks[root] = Mark::OnStack;
+
+            while let Some((state, class_idx)) = stack.last_mut() {
+                if *class_idx == self.class_count {
+                    marks[*state] = Mark::Done;
+                    stack.pop();
+                    continue;
+                }
+
+                let dest = self.class_transition(*state, *class_idx);
+                *class_idx += 1;
+                if !included[dest] {
+                    continue;
+                }
+                match marks[dest] {
+                    Mark::OnStack => return true,
+                    Mark::Unvisited => {
+                        marks[dest] = Mark::OnStack;
+                        stack.push((dest, 0));
+                    }
+                    Mark::Done => {}
+                }
+            }
+        }
+
+        false
+    }
+}
@@ -0,0 +1,194 @@
+use bimap::BiMap;
+use std::collections::{HashMap, VecDeque};
+
+use crate::compare::merged_alphabet;
+use crate::dfa::{Dfa, StateInfo};
+
+/// Key used for the implicit dead state of a machine whose alphabet lacks a character.
+const DEAD_STATE_KEY: &str = "∅";
+
+/// Boolean operations that can be applied to the languages of two DFAs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BooleanOp {
+    Intersection,
+    Union,
+    Difference,
+    SymmetricDifference,
+}
+
+impl BooleanOp {
+    /// Decides whether a product state accepts, given whether each component accepts.
+    fn accepts(self, first: bool, second: bool) -> bool {
+        match self {
+            BooleanOp::Intersection => first && second,
+            BooleanOp::Union => first || second,
+            BooleanOp::Difference => first && !second,
+            BooleanOp::SymmetricDifference => first != second,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            BooleanOp::Intersection => "∩",
+            BooleanOp::Union => "∪",
+            BooleanOp::Difference => "∖",
+            BooleanOp::SymmetricDifference => "⊕",
+        }
+    }
+}
+
+impl Dfa {
+    /// Returns a DFA accepting exactly the strings over the alphabet that `self` rejects.
+    ///
+    /// The transition table is total, so inverting acceptance of every state is sufficient. Strings
+    /// containing characters outside the alphabet are still rejected. State keys, labels and
+    /// outputs are kept, and the name and description note that acceptance was inverted. Tokens
+    /// are dropped: a token names what an accept state recognizes, which no longer holds once its
+    /// acceptance is inverted.
+    pub fn complement(&self) -> Dfa {
+        let state_properties = self
+            .state_properties
+            .iter()
+            .map(|info| StateInfo {
+                label: info.label.clone(),
+                accept: !info.accept,
+                token: None,
+                output: info.output.clone(),
+            })
+            .collect();
+
+        let (transition_table, transition_outputs) = self.expanded_transitions();
+        let note = "Complement: acceptance of every state is inverted.";
+        Dfa::new(
+            format!("¬{}", self.name),
+            Some(match &self.description {
+                Some(description) => format!("{}\n{}", description, note),
+                None => note.to_string(),
+            }),
+            self.alphabet.clone(),
+            self.state_keys.clone(),
+            self.start_state_idx,
+            transition_table,
+            state_properties,
+        )
+        .with_transition_outputs(transition_outputs)
+    }
+
+    /// Returns a DFA accepting the strings accepted by both `self` and `other`.
+    pub fn intersection(&self, other: &Dfa) -> Dfa {
+        product(self, other, BooleanOp::Intersection)
+    }
+
+    /// Returns a DFA accepting the strings accepted by either `self` or `other`.
+    pub fn union(&self, other: &Dfa) -> Dfa {
+        product(self, other, BooleanOp::Union)
+    }
+
+    /// Returns a DFA accepting the strings accepted by `self` but not by `other`.
+    pub fn difference(&self, other: &Dfa) -> Dfa {
+        product(self, other, BooleanOp::Difference)
+    }
+
+    /// Returns a DFA accepting the strings accepted by exactly one of `self` and `other`.
+    pub fn symmetric_difference(&self, other: &Dfa) -> Dfa {
+        product(self, other, BooleanOp::SymmetricDifference)
+    }
+}
+
+/// Builds the product automaton of two DFAs, accepting according to `op`.
+///
+/// The result runs over the union of both alphabets. A character missing from one machine's
+/// alphabet sends that machine to an implicit dead state, shown as `∅` in the composite state
+/// keys (e.g. `(q1,∅)`). If two product states would get the same key, the later one is suffixed
+/// with its index (e.g. `(a,b)#3`). Only product states reachable from the start state are created.
+pub fn product(first: &Dfa, second: &Dfa, op: BooleanOp) -> Dfa {
+    type Pair = (Option<usize>, Option<usize>);
+
+    let alphabet = merged_alphabet(first, second);
+    let alphabet_bimap: BiMap<char, usize> = alphabet
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, c)| (c, i))
+        .collect();
+
+    let step = |dfa: &Dfa, state: Option<usize>, c: char| state.and_then(|s| dfa.next_state(s, c));
+
+    // product state -> product state index, assigned in breadth-first order
+    let mut pair_indices: HashMap<Pair, usize> = HashMap::new();
+    let mut pairs: Vec<Pair> = Vec::new();
+    let mut queue: VecDeque<Pair> = VecDeque::new();
+    let mut transition_table = Vec::new();
+
+    let start = (Some(first.start_state_idx), Some(second.start_state_idx));
+    pair_indices.insert(start, 0);
+    pairs.push(start);
+    queue.push_back(start);
+
+    while let Some(pair) = queue.pop_front() {
+        for &c in &alphabet {
+            let next = (step(first, pair.0, c), step(second, pair.1, c));
+            let next_idx = *pair_indices.entry(next).or_insert_with(|| {
+                pairs.push(next);
+                queue.push_back(next);
+                pairs.len() - 1
+            });
+            transition_table.push(next_idx);
+        }
+    }
+
+    let mut state_keys = BiMap::new();
+    let mut state_properties = Vec::with_capacity(pairs.len());
+
+    for (idx, &(a, b)) in pairs.iter().enumerate() {
+        let accept = op.accepts(
+            a.is_some_and(|s| first.accept_states[s]),
+            b.is_some_and(|s| second.accept_states[s]),
+        );
+
+        let mut key = format!("({},{})", state_key(first, a), state_key(second, b));
+        if state_keys.contains_left(&key) {
+            // component keys containing ',' or ')' can spell the same key for different pairs.
+            // a key ending in the (unique) index never ends in ')', so it cannot collide again
+            key = format!("{}#{}", key, idx);
+        }
+        let label = format!("({}, {})", state_label(first, a), state_label(second, b));
+
+        state_keys.insert(key, idx);
+        state_properties.push(StateInfo {
+            label: Some(label),
+            accept,
+            token: None,
+            output: None,
+        });
+    }
+
+    Dfa::new(
+        format!("{} {} {}", first.name, op.symbol(), second.name),
+        Some(format!(
+            "Product of '{}' {} '{}'",
+            first.name,
+            op.symbol(),
+            second.name
+        )),
+        alphabet_bimap,
+        state_keys,
+        0,
+        transition_table,
+        state_properties,
+    )
+}
+
+/// Returns the key of a component state, or the dead state key for `None`.
+fn state_key(dfa: &Dfa, state: Option<usize>) -> &str {
+    state
+        .and_then(|s| dfa.state_keys.get_by_right(&s))
+        .map_or(DEAD_STATE_KEY, String::as_str)
+}
+
+/// Returns the label of a component state, falling back to its key.
+fn state_label(dfa: &Dfa, state: Option<usize>) -> &str {
+    state
+        .and_then(|s| dfa.state_properties[s].label.as_deref())
+        .unwrap_or_else(|| state_key(dfa, state))
+}
@@ -0,0 +1,197 @@
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+
+use crate::dfa::Dfa;
+
+/// A transition table stored over alphabet equivalence classes, as computed by [`compress`].
+pub(crate) struct CompressedTable {
+    /// alphabet index -> class index
+    pub char_classes: Vec<usize>,
+    pub class_count: usize,
+    /// (state_idx * class_count) + class_idx -> next_state_idx
+    pub class_table: Vec<usize>,
+    /// Mealy outputs, indexed like `class_table`. empty if no transition has an output
+    pub class_outputs: Vec<Option<String>>,
+}
+
+/// Groups the symbols of a transition table indexed by `(state_idx * alphabet_size) + alpha_idx`
+/// into equivalence classes and returns the table over the classes.
+///
+/// Two symbols are equivalent if they lead to the same state with the same Mealy output from
+/// every state, so a class can stand in for each of its symbols. Classes are numbered in the
+/// order of their first symbol.
+pub(crate) fn compress(
+    transition_table: &[usize],
+    transition_outputs: &[Option<String>],
+    alphabet_size: usize,
+) -> CompressedTable {
+    let state_count = transition_table
+        .len()
+        .checked_div(alphabet_size)
+        .unwrap_or(0);
+
+    // column of a symbol: (destination, output) from every state, in state order
+    let column = |alpha_idx: usize| -> Vec<(usize, Option<&str>)> {
+        (0..state_count)
+            .map(|state_idx| {
+                let table_idx = (state_idx * alphabet_size) + alpha_idx;
+                let output = transition_outputs.get(table_idx).and_then(Option::as_deref);
+                (transition_table[table_idx], output)
+            })
+            .collect()
+    };
+
+    let mut class_of_column: HashMap<Vec<(usize, Option<&str>)>, usize> = HashMap::new();
+    let mut representatives = Vec::new();
+    let char_classes: Vec<usize> = (0..alphabet_size)
+        .map(|alpha_idx| {
+            *class_of_column.entry(column(alpha_idx)).or_insert_with(|| {
+                representatives.push(alpha_idx);
+                representatives.len() - 1
+            })
+        })
+        .collect();
+    let class_count = representatives.len();
+
+    let mut compressed_table = Vec::with_capacity(state_count * class_count);
+    let mut compressed_outputs = Vec::new();
+    for state_idx in 0..state_count {
+        for &alpha_idx in &representatives {
+            let table_idx = (state_idx * alphabet_size) + alpha_idx;
+            compressed_table.push(transition_table[table_idx]);
+            if !transition_outputs.is_empty() {
+                compressed_outputs.push(transition_outputs[table_idx].clone());
+            }
+        }
+    }
+
+    CompressedTable {
+        char_classes,
+        class_count,
+        class_table: compressed_table,
+        class_outputs: compressed_outputs,
+    }
+}
+
+/// Partitions the symbols of an alphabet into classes by successive refinement, so that a table
+/// can be built with one column per class directly and then finished with [`merge`].
+pub(crate) struct ClassRefiner {
+    /// alphabet index -> class id. ids are not contiguous until [`ClassRefiner::finish`]
+    char_classes: Vec<usize>,
+    next_class: usize,
+}
+
+impl ClassRefiner {
+    /// Starts with all `alphabet_size` symbols in a single class.
+    pub(crate) fn new(alphabet_size: usize) -> Self {
+        ClassRefiner {
+            char_classes: vec![0; alphabet_size],
+            next_class: 1,
+        }
+    }
+
+    /// Splits the classes so that two symbols only stay together if they have the same group key,
+    /// or are both absent from `groups`. Each symbol may appear at most once.
+    pub(crate) fn refine<K, I>(&mut self, groups: I)
+    where
+        K: Hash + Eq,
+        I: IntoIterator<Item = (usize, K)>,
+    {
+        let mut splits: HashMap<(usize, K), usize> = HashMap::new();
+        for (alpha_idx, key) in groups {
+            let class_idx = &mut self.char_classes[alpha_idx];
+            *class_idx = *splits.entry((*class_idx, key)).or_insert_with(|| {
+                self.next_class += 1;
+                self.next_class - 1
+            });
+        }
+    }
+
+    /// Returns the class of each symbol and the number of classes, numbered in the order of
+    /// their first symbol.
+    pub(crate) fn finish(self) -> (Vec<usize>, usize) {
+        let mut renumbered: HashMap<usize, usize> = HashMap::new();
+        let char_classes = self
+            .char_classes
+            .iter()
+            .map(|&class_id| {
+                let next_class = renumbered.len();
+                *renumbered.entry(class_id).or_insert(next_class)
+            })
+            .collect();
+        (char_classes, renumbered.len())
+    }
+}
+
+/// Merges the classes of a table built over `char_classes` (indexed by
+/// `(state_idx * class_count) + class_idx`) that turn out to be equivalent, like [`compress`]
+/// does for a table with one column per symbol.
+pub(crate) fn merge(
+    char_classes: &[usize],
+    class_count: usize,
+    class_table: &[usize],
+    class_outputs: &[Option<String>],
+) -> CompressedTable {
+    let merged = compress(class_table, class_outputs, class_count);
+    CompressedTable {
+        char_classes: char_classes
+            .iter()
+            .map(|&class_idx| merged.char_classes[class_idx])
+            .collect(),
+        ..merged
+    }
+}
+
+impl Dfa {
+    /// Returns the equivalence class of the symbol at `alpha_idx`.
+    pub fn class_of(&self, alpha_idx: usize) -> usize {
+        self.char_classes[alpha_idx]
+    }
+
+    /// Returns the symbols of each equivalence class, indexed by class.
+    pub fn class_members(&self) -> Vec<BTreeSet<char>> {
+        let mut members = vec![BTreeSet::new(); self.class_count];
+        for (&c, &alpha_idx) in self.alphabet.iter() {
+            members[self.char_classes[alpha_idx]].insert(c);
+        }
+        members
+    }
+
+    /// Returns the destination state of the transition from `state_idx` on any symbol of the
+    /// class `class_idx`.
+    pub fn class_transition(&self, state_idx: usize, class_idx: usize) -> usize {
+        self.class_table[(state_idx * self.class_count) + class_idx]
+    }
+
+    /// Returns the Mealy output of the transition from `state_idx` on any symbol of the class
+    /// `class_idx`.
+    pub fn class_transition_output(&self, state_idx: usize, class_idx: usize) -> Option<&str> {
+        self.class_outputs
+            .get((state_idx * self.class_count) + class_idx)
+            .and_then(Option::as_deref)
+    }
+
+    /// Returns the transition table and Mealy outputs indexed by
+    /// `(state_idx * alphabet_size) + alpha_idx`, i.e. with one column per symbol, as accepted by
+    /// [`Dfa::new`] and [`Dfa::with_transition_outputs`].
+    pub fn expanded_transitions(&self) -> (Vec<usize>, Vec<Option<String>>) {
+        let state_count = self.state_keys.len();
+        let alphabet_size = self.alphabet.len();
+
+        let mut transition_table = Vec::with_capacity(state_count * alphabet_size);
+        let mut transition_outputs = Vec::new();
+        for state_idx in 0..state_count {
+            for alpha_idx in 0..alphabet_size {
+                transition_table.push(self.transition(state_idx, alpha_idx));
+                if !self.class_outputs.is_empty() {
+                    transition_outputs.push(
+                        self.transition_output(state_idx, alpha_idx)
+                            .map(str::to_string),
+                    );
+                }
+            }
+        }
+
+        (transition_table, transition_outputs)
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::{BTreeSet, HashMap, VecDeque, hash_map::Entry};
+
+use crate::dfa::Dfa;
+
+/// Identifies one of the two machines being compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Machine {
+    First,
+    Second,
+}
+
+/// The result of comparing the languages of two DFAs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Equivalence {
+    Equivalent,
+    /// `witness` is a shortest string accepted by exactly one of the machines.
+    Different {
+        witness: String,
+        accepted_by: Machine,
+    },
+}
+
+/// The result of checking whether one DFA's language is contained in another's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inclusion {
+    Included,
+    /// `witness` is a shortest string accepted by the first machine but not the second.
+    NotIncluded {
+        witness: String,
+    },
+}
+
+/// Checks whether two DFAs accept the same language.
+///
+/// The alphabets are aligned by character, and a character missing from one machine's alphabet
+/// sends that machine to an implicit dead state. If the languages differ, the returned witness is
+/// the shortest (and, among those, lexicographically smallest) distinguishing string.
+pub fn check_equivalence(first: &Dfa, second: &Dfa) -> Equivalence {
+    match find_witness(first, second, |a, b| a != b) {
+        None => Equivalence::Equivalent,
+        Some((witness, first_accepts, _)) => Equivalence::Different {
+            witness,
+            accepted_by: if first_accepts {
+                Machine::First
+            } else {
+                Machine::Second
+            },
+        },
+    }
+}
+
+/// Checks whether every string accepted by `subset` is also accepted by `superset`.
+///
+/// Alphabets are aligned the same way as in [`check_equivalence`]. If inclusion fails, the
+/// returned witness is the shortest (and, among those, lexicographically smallest) string in
+/// L(subset) \ L(superset).
+pub fn check_inclusion(subset: &Dfa, superset: &Dfa) -> Inclusion {
+    match find_witness(subset, superset, |a, b| a && !b) {
+        None => Inclusion::Included,
+        Some((witness, _, _)) => Inclusion::NotIncluded { witness },
+    }
+}
+
+/// Returns the sorted union of the alphabets of both machines.
+pub(crate) fn merged_alphabet(first: &Dfa, second: &Dfa) -> Vec<char> {
+    let chars: BTreeSet<char> = first
+        .alphabet
+        .left_values()
+        .chain(second.alphabet.left_values())
+        .cloned()
+        .collect();
+    chars.into_iter().collect()
+}
+
+/// Searches the product of both machines breadth-first for the shortest string whose acceptance
+/// by each machine satisfies `is_witness`. Returns the string and whether each machine accepts it.
+///
+/// `None` in a product state represents the implicit dead state.
+pub(crate) fn find_witness(
+    first: &Dfa,
+    second: &Dfa,
+    is_witness: impl Fn(bool, bool) -> bool,
+) -> Option<(String, bool, bool)> {
+    type Pair = (Option<usize>, Option<usize>);
+
+    let alphabet = merged_alphabet(first, second);
+    let accepts = |dfa: &Dfa, state: Option<usize>| state.is_some_and(|s| dfa.accept_states[s]);
+    let step = |dfa: &Dfa, state: Option<usize>, c: char| state.and_then(|s| dfa.next_state(s, c));
+
+    // product state -> (previous product state, consumed char)
+    let mut parents: HashMap<Pair, Option<(Pair, char)>> = HashMap::new();
+    let mut queue: VecDeque<Pair> = VecDeque::new();
+
+    let start = (Some(first.start_state_idx), Some(second.start_state_idx));
+    parents.insert(start, None);
+    queue.push_back(start);
+
+    while let Some(pair) = queue.pop_front() {
+        let (first_accepts, second_accepts) = (accepts(first, pair.0), accepts(second, pair.1));
+        if is_witness(first_accepts, second_accepts) {
+            // walk the parent chain back to the start to rebuild the input
+            let mut chars = Vec::new();
+            let mut current = pair;
+            while let Some(&Some((prev, c))) = parents.get(&current) {
+                chars.push(c);
+                current = prev;
+            }
+            return Some((
+                chars.into_iter().rev().collect(),
+                first_accepts,
+                second_accepts,
+            ));
+        }
+
+        for &c in &alphabet {
+            let next = (step(first, pair.0, c), step(second, pair.1, c));
+            if let Entry::Vacant(entry) = parents.entry(next) {
+                entry.insert(Some((pair, c)));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
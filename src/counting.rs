@@ -0,0 +1,50 @@
+use crate::dfa::Dfa;
+
+impl Dfa {
+    /// Returns the number of accepted strings of each length `0..=max_len`.
+    ///
+    /// Counts are computed with a forward dynamic-programming pass over the transition table,
+    /// tracking how many strings of the current length lead to each state. Counts that do not fit
+    /// in a `u128` saturate at `u128::MAX`.
+    pub fn count_accepted_by_length(&self, max_len: usize) -> Vec<u128> {
+        let state_count = self.state_keys.len();
+        let class_sizes: Vec<u128> = self
+            .class_members()
+            .iter()
+            .map(|members| members.len() as u128)
+            .collect();
+
+        let accepted = |paths: &[u128]| {
+            paths
+                .iter()
+                .zip(&self.accept_states)
+                .filter(|&(_, &accept)| accept)
+                .fold(0u128, |total, (&n, _)| total.saturating_add(n))
+        };
+
+        // paths[state] = number of strings of the current length leading from the start to state
+        let mut paths = vec![0u128; state_count];
+        paths[self.start_state_idx] = 1;
+
+        let mut counts = Vec::with_capacity(max_len + 1);
+        counts.push(accepted(&paths));
+
+        for _ in 0..max_len {
+            let mut next = vec![0u128; state_count];
+            for (src, &src_paths) in paths.iter().enumerate() {
+                if src_paths == 0 {
+                    continue;
+                }
+                // every symbol of a class leads to the same state
+                for (class_idx, &class_size) in class_sizes.iter().enumerate() {
+                    let dest = self.class_transition(src, class_idx);
+                    next[dest] = next[dest].saturating_add(src_paths.saturating_mul(class_size));
+                }
+            }
+            paths = next;
+            counts.push(accepted(&paths));
+        }
+
+        counts
+    }
+}
@@ -1,15 +1,56 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use bimap::BiMap;
+use std::collections::HashMap;
 
+use crate::classes::{self, CompressedTable};
+use crate::dot_generator::format_char_set;
 use crate::parser::{self, Fsm};
 
 #[derive(Debug, Clone)]
 pub struct StateInfo {
     pub label: Option<String>,
     pub accept: bool,
+    /// Name of the token recognized when a lexer stops in this (accept) state.
+    pub token: Option<String>,
+    /// Moore output emitted when this state is entered (or is the start state).
+    pub output: Option<String>,
 }
 
-#[derive(Debug)]
+/// A single transition taken while tracing a run of a DFA.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep<'a> {
+    /// Key of the state the transition starts from.
+    pub from: &'a str,
+    /// The consumed input character.
+    pub symbol: char,
+    /// Key of the state the transition leads to.
+    pub to: &'a str,
+}
+
+/// The full path taken by a DFA on an input string, as returned by [`Dfa::run_with_trace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace<'a> {
+    /// Key of the start state.
+    pub start: &'a str,
+    /// The transitions taken, one per consumed character.
+    pub steps: Vec<TraceStep<'a>>,
+    /// Position (in characters) and value of the input character that is not in the alphabet,
+    /// if one aborted the run.
+    pub unknown_char: Option<(usize, char)>,
+    pub accepted: bool,
+}
+
+impl Trace<'_> {
+    /// Returns the key of the state the run ended in (or was aborted in).
+    pub fn end(&self) -> &str {
+        self.steps.last().map_or(self.start, |step| step.to)
+    }
+}
+
+/// Marks a Latin-1 character that is not in the alphabet in [`Dfa::latin1_classes`].
+pub const NO_CLASS: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
 pub struct Dfa {
     pub name: String,
     pub description: Option<String>,
@@ -21,13 +62,141 @@ pub struct Dfa {
     // [state1_is_accept, state2_is_accept, ...]
     pub accept_states: Vec<bool>,
 
-    // (state_idx * alphabet_len) + alphabet_idx -> next_state_idx
-    pub transition_table: Vec<usize>,
+    // alphabet index -> equivalence class. symbols in the same class behave identically in every
+    // state, so the transition table only needs one column per class
+    pub char_classes: Vec<usize>,
+    pub class_count: usize,
+
+    // runtime lookup from a character to its class: a dense table indexed by the code point for
+    // Latin-1 characters (NO_CLASS if not in the alphabet), and a map for all other characters
+    pub latin1_classes: Vec<usize>,
+    pub other_classes: HashMap<char, usize>,
+
+    // (state_idx * class_count) + class_idx -> next_state_idx
+    pub class_table: Vec<usize>,
 
     pub state_properties: Vec<StateInfo>, // index -> state properties
+
+    // [state1_is_trap, ...]; a trap state cannot reach any accept state
+    pub trap_states: Vec<bool>,
+
+    // Mealy outputs, indexed like class_table. empty if no transition has an output
+    pub class_outputs: Vec<Option<String>>,
 }
 
 impl Dfa {
+    /// Creates a DFA from its components.
+    ///
+    /// `transition_table` has one entry per state and symbol, indexed by
+    /// `(state_idx * alphabet_size) + alpha_idx`; it is stored compressed over the equivalence
+    /// classes of the alphabet. Acceptance of each state is taken from `state_properties`, and the
+    /// trap states (states from which no accept state is reachable) are detected here so that runs
+    /// can stop as soon as the input can no longer be accepted.
+    pub fn new(
+        name: String,
+        description: Option<String>,
+        alphabet: BiMap<char, usize>,
+        state_keys: BiMap<String, usize>,
+        start_state_idx: usize,
+        transition_table: Vec<usize>,
+        state_properties: Vec<StateInfo>,
+    ) -> Dfa {
+        let compressed = classes::compress(&transition_table, &[], alphabet.len());
+        Dfa::from_classes(
+            name,
+            description,
+            alphabet,
+            state_keys,
+            start_state_idx,
+            compressed,
+            state_properties,
+        )
+    }
+
+    /// Creates a DFA from a transition table that is already stored over equivalence classes,
+    /// like [`Dfa::new`] but without building the table with one column per symbol first.
+    pub(crate) fn from_classes(
+        name: String,
+        description: Option<String>,
+        alphabet: BiMap<char, usize>,
+        state_keys: BiMap<String, usize>,
+        start_state_idx: usize,
+        table: CompressedTable,
+        state_properties: Vec<StateInfo>,
+    ) -> Dfa {
+        let accept_states = state_properties.iter().map(|info| info.accept).collect();
+        let mut dfa = Dfa {
+            name,
+            description,
+            alphabet,
+            state_keys,
+            start_state_idx,
+            accept_states,
+            char_classes: table.char_classes,
+            class_count: table.class_count,
+            latin1_classes: Vec::new(),
+            other_classes: HashMap::new(),
+            class_table: table.class_table,
+            state_properties,
+            trap_states: Vec::new(),
+            class_outputs: table.class_outputs,
+        };
+        dfa.build_char_lookup();
+        dfa.trap_states = dfa.coreachable_states().iter().map(|&c| !c).collect();
+        dfa
+    }
+
+    /// Sets the Mealy outputs of the transitions, indexed like the `transition_table` passed to
+    /// [`Dfa::new`]. An empty vector means that no transition has an output.
+    ///
+    /// Symbols that lead to the same states but with different outputs are split into separate
+    /// equivalence classes.
+    pub fn with_transition_outputs(mut self, transition_outputs: Vec<Option<String>>) -> Dfa {
+        if transition_outputs.is_empty() {
+            return self;
+        }
+        let (transition_table, _) = self.expanded_transitions();
+        assert!(
+            transition_outputs.len() == transition_table.len(),
+            "transition outputs must be empty or match the transition table"
+        );
+        let compressed =
+            classes::compress(&transition_table, &transition_outputs, self.alphabet.len());
+        self.char_classes = compressed.char_classes;
+        self.class_count = compressed.class_count;
+        self.class_table = compressed.class_table;
+        self.class_outputs = compressed.class_outputs;
+        self.build_char_lookup();
+        self
+    }
+
+    /// Fills `latin1_classes` and `other_classes` from the alphabet and its classes.
+    fn build_char_lookup(&mut self) {
+        self.latin1_classes = vec![NO_CLASS; 256];
+        self.other_classes.clear();
+        for (&c, &alpha_idx) in self.alphabet.iter() {
+            let class_idx = self.char_classes[alpha_idx];
+            match u8::try_from(c) {
+                Ok(byte) => self.latin1_classes[byte as usize] = class_idx,
+                Err(_) => {
+                    self.other_classes.insert(c, class_idx);
+                }
+            }
+        }
+    }
+
+    /// Returns the equivalence class of `c`, or `None` if `c` is not in the alphabet.
+    ///
+    /// Latin-1 characters (which include ASCII) are looked up in a dense table; only other
+    /// characters need a hash lookup.
+    #[inline]
+    pub fn class_of_char(&self, c: char) -> Option<usize> {
+        match u8::try_from(c) {
+            Ok(byte) => Some(self.latin1_classes[byte as usize]).filter(|&idx| idx != NO_CLASS),
+            Err(_) => self.other_classes.get(&c).copied(),
+        }
+    }
+
     /// Parses a DFA from a YAML string specification.
     pub fn from_yaml(yaml_content: &str) -> Result<Fsm> {
         parser::from_yaml(yaml_content)
@@ -39,68 +208,190 @@ impl Dfa {
         I: IntoIterator<Item = char>,
     {
         let mut current_state = self.start_state_idx;
+        let class_count = self.class_count;
 
-        let alphabet_size = self.alphabet.len();
+        for c in input {
+            let Some(class_idx) = self.class_of_char(c) else {
+                return false;
+            };
+            current_state = self.class_table[(current_state * class_count) + class_idx];
+            if self.trap_states[current_state] {
+                return false;
+            }
+        }
 
-        let mut prev_char: char;
-        let mut prev_index: usize;
-        let mut iter = input.into_iter();
+        self.accept_states[current_state]
+    }
 
-        // handle the first character separately to avoid using Option in the loop
-        if let Some(c) = iter.next() {
-            if let Some(&idx) = self.alphabet.get_by_left(&c) {
-                prev_char = c;
-                prev_index = idx;
-                current_state = self.transition_table[(current_state * alphabet_size) + idx];
-            } else {
+    /// Runs the DFA on Latin-1 encoded bytes and returns true if accepted, false otherwise.
+    ///
+    /// Each byte is read as the Latin-1 character with the same code point (so ASCII bytes are
+    /// ASCII characters), and looked up directly in the dense table without decoding. To run the
+    /// DFA on UTF-8 text, use [`Dfa::run`] with `str::chars`, or compile it with
+    /// [`Dfa::to_byte_dfa`] and use [`ByteDfa::run`](crate::utf8::ByteDfa::run).
+    pub fn run_bytes(&self, input: &[u8]) -> bool {
+        let mut current_state = self.start_state_idx;
+        let class_count = self.class_count;
+
+        for &byte in input {
+            let class_idx = self.latin1_classes[byte as usize];
+            if class_idx == NO_CLASS {
+                return false;
+            }
+            current_state = self.class_table[(current_state * class_count) + class_idx];
+            if self.trap_states[current_state] {
                 return false;
             }
-        } else {
-            return self.accept_states[current_state];
         }
 
-        // handle remaining characters
-        for c in iter {
-            let alphabet_idx = if c == prev_char {
-                prev_index
-            } else {
-                if let Some(&idx) = self.alphabet.get_by_left(&c) {
-                    prev_char = c;
-                    prev_index = idx;
-                    idx
-                } else {
-                    return false;
-                }
+        self.accept_states[current_state]
+    }
+
+    /// Returns the length of the shortest prefix of `input` after which the input can no longer
+    /// be accepted, however it continues, or `None` if it never becomes unrecoverable.
+    ///
+    /// The input becomes unrecoverable when the run enters a trap state or reads a character that
+    /// is not in the alphabet. A result of `Some(0)` means the DFA accepts nothing at all. Like
+    /// [`Dfa::run`], processing stops as soon as the answer is known.
+    pub fn unrecoverable_position<I>(&self, input: I) -> Option<usize>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut current_state = self.start_state_idx;
+        if self.trap_states[current_state] {
+            return Some(0);
+        }
+
+        for (position, c) in input.into_iter().enumerate() {
+            match self.next_state(current_state, c) {
+                Some(next_state) if !self.trap_states[next_state] => current_state = next_state,
+                _ => return Some(position + 1),
+            }
+        }
+
+        None
+    }
+
+    /// Runs the DFA on the given input string and records every transition taken.
+    ///
+    /// The run stops at the first character that is not in the alphabet, in which case the input
+    /// is rejected and the trace records the position of that character.
+    pub fn run_with_trace<I>(&self, input: I) -> Trace<'_>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut current_state = self.start_state_idx;
+        let mut steps = Vec::new();
+
+        for (position, c) in input.into_iter().enumerate() {
+            let Some(next_state) = self.next_state(current_state, c) else {
+                return Trace {
+                    start: self.state_key(self.start_state_idx),
+                    steps,
+                    unknown_char: Some((position, c)),
+                    accepted: false,
+                };
             };
+            steps.push(TraceStep {
+                from: self.state_key(current_state),
+                symbol: c,
+                to: self.state_key(next_state),
+            });
+            current_state = next_state;
+        }
 
-            current_state = self.transition_table[(current_state * alphabet_size) + alphabet_idx];
+        Trace {
+            start: self.state_key(self.start_state_idx),
+            steps,
+            unknown_char: None,
+            accepted: self.accept_states[current_state],
         }
+    }
 
-        self.accept_states[current_state]
+    /// Returns the Mealy output of the transition from `state_idx` on the symbol at `alpha_idx`.
+    pub fn transition_output(&self, state_idx: usize, alpha_idx: usize) -> Option<&str> {
+        self.class_transition_output(state_idx, self.char_classes[alpha_idx])
+    }
+
+    /// Returns true if any state (Moore) or transition (Mealy) has an output.
+    pub fn has_outputs(&self) -> bool {
+        !self.class_outputs.is_empty()
+            || self
+                .state_properties
+                .iter()
+                .any(|info| info.output.is_some())
     }
 
-    /// Prints a human-readable representation of the DFA's transition table.
+    /// Runs the DFA as a Mealy/Moore machine and returns the outputs it emits on the input.
+    ///
+    /// The output of the start state comes first. Then, for every input character, the output of
+    /// the transition taken is followed by the output of the state it leads to. Transitions and
+    /// states without an output emit nothing. Fails on a character that is not in the alphabet.
+    pub fn transduce<I>(&self, input: I) -> Result<Vec<&str>>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut current_state = self.start_state_idx;
+        let mut outputs: Vec<&str> = self.state_properties[current_state]
+            .output
+            .as_deref()
+            .into_iter()
+            .collect();
+
+        for (position, c) in input.into_iter().enumerate() {
+            let alpha_idx = *self.alphabet.get_by_left(&c).ok_or_else(|| {
+                anyhow!(
+                    "Character {:?} at position {} is not in the alphabet",
+                    c,
+                    position
+                )
+            })?;
+            outputs.extend(self.transition_output(current_state, alpha_idx));
+            current_state = self.transition(current_state, alpha_idx);
+            outputs.extend(self.state_properties[current_state].output.as_deref());
+        }
+
+        Ok(outputs)
+    }
+
+    /// Returns the key of the state at `state_idx`.
+    pub fn state_key(&self, state_idx: usize) -> &str {
+        self.state_keys
+            .get_by_right(&state_idx)
+            .map_or("ERR", |s| s.as_str())
+    }
+
+    /// Returns the destination state of the transition from `state_idx` on the symbol at `alpha_idx`.
+    pub fn transition(&self, state_idx: usize, alpha_idx: usize) -> usize {
+        self.class_transition(state_idx, self.char_classes[alpha_idx])
+    }
+
+    /// Returns the state reached from `state_idx` on `c`, or `None` if `c` is not in the alphabet.
+    pub fn next_state(&self, state_idx: usize, c: char) -> Option<usize> {
+        self.class_of_char(c)
+            .map(|class_idx| self.class_transition(state_idx, class_idx))
+    }
+
+    /// Prints a human-readable representation of the DFA's transition table, with one column per
+    /// equivalence class of symbols.
     pub fn print_transition_table(&self) {
         println!("DFA: {}", self.name);
 
-        let alphabet_size = self.alphabet.len();
-        let mut alphabet_header: Vec<char> = vec![' '; alphabet_size];
-        for (c, &idx) in self.alphabet.iter() {
-            if c == &' ' {
-                alphabet_header[idx] = '␣'; // Use a special symbol for space
-            } else if idx < alphabet_header.len() {
-                alphabet_header[idx] = *c;
-            }
-        }
         const CHARS_FOR_KEY: usize = 18;
         const PREFIX_WIDTH: usize = 4; // "--> " or "    "
         const STATE_COL_WIDTH: usize = CHARS_FOR_KEY + 2; // chars for key + 1 for '*' + 1 space
         const CELL_WIDTH: usize = CHARS_FOR_KEY + 1; // chars for key + 1 space
 
+        let class_header: Vec<String> = self
+            .class_members()
+            .iter()
+            .map(|chars| truncate_chars(&format_char_set(chars), CHARS_FOR_KEY).to_string())
+            .collect();
+
         print!("{:<PREFIX_WIDTH$}", ""); // padding for the prefix column
         print!("{:<STATE_COL_WIDTH$}", "STATE");
-        for c in &alphabet_header {
-            print!("{:<CELL_WIDTH$}", c);
+        for header in &class_header {
+            print!("{:<CELL_WIDTH$}", header);
         }
         println!();
 
@@ -119,11 +410,7 @@ impl Dfa {
                 .map_or("ERR", |s| s.as_str());
 
             // truncate state key
-            let trunc_key = if state_key.len() > CHARS_FOR_KEY {
-                &state_key[..CHARS_FOR_KEY]
-            } else {
-                state_key
-            };
+            let trunc_key = truncate_chars(state_key, CHARS_FOR_KEY);
 
             let state_display = format!(
                 "{}{}",
@@ -133,19 +420,15 @@ impl Dfa {
 
             print!("{:<STATE_COL_WIDTH$}", state_display);
 
-            for alpha_idx in 0..alphabet_size {
-                let dest_idx = self.transition_table[(src_idx * alphabet_size) + alpha_idx];
+            for class_idx in 0..self.class_count {
+                let dest_idx = self.class_transition(src_idx, class_idx);
 
                 let dest_key = self
                     .state_keys
                     .get_by_right(&dest_idx)
                     .map_or("ERR", |s| s.as_str());
                 // truncate dest key
-                let trunc_dest_key = if dest_key.len() > CHARS_FOR_KEY {
-                    &dest_key[..CHARS_FOR_KEY]
-                } else {
-                    dest_key
-                };
+                let trunc_dest_key = truncate_chars(dest_key, CHARS_FOR_KEY);
 
                 print!("{:<CELL_WIDTH$}", trunc_dest_key);
             }
@@ -153,3 +436,11 @@ impl Dfa {
         }
     }
 }
+
+/// Truncates a string to at most `max_chars` characters without splitting a multi-byte character.
+fn truncate_chars(s: &str, max_chars: usize) -> &str {
+    match s.char_indices().nth(max_chars) {
+        Some((byte_idx, _)) => &s[..byte_idx],
+        None => s,
+    }
+}
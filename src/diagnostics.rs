@@ -0,0 +1,88 @@
+use crate::dfa::Dfa;
+
+/// Why an input was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// The character at the reported position led into a trap state, from which no accept state
+    /// is reachable. A position of 0 with no characters read means the start state is a trap.
+    Trapped,
+    /// The character at the reported position is not in the alphabet.
+    UnknownChar(char),
+    /// The whole input was read, ending in a non-accepting state.
+    EndedInNonAccepting,
+}
+
+/// Explains where and why a DFA rejected an input, as returned by [`Dfa::explain_rejection`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectionReport {
+    pub reason: RejectionReason,
+    /// Index (in characters) of the offending character, or the input length if the input ended
+    /// in a non-accepting state.
+    pub position: usize,
+    /// The state the DFA was in at that position, before reading the offending character.
+    pub state_idx: usize,
+    /// The symbols that, read in `state_idx`, would have kept the input viable (i.e. lead to a
+    /// state that is not a trap), in alphabet order.
+    pub viable_symbols: Vec<char>,
+}
+
+impl Dfa {
+    /// Runs the DFA on the given input and, if it is rejected, explains why.
+    ///
+    /// Returns `None` if the input is accepted. Otherwise the report identifies the first point
+    /// at which the input became impossible to accept (or the end of the input) and the symbols
+    /// that would have kept it viable there.
+    pub fn explain_rejection<I>(&self, input: I) -> Option<RejectionReport>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut current_state = self.start_state_idx;
+        let report = |reason, position, state_idx| RejectionReport {
+            reason,
+            position,
+            state_idx,
+            viable_symbols: self.viable_symbols(state_idx),
+        };
+
+        if self.trap_states[current_state] {
+            return Some(report(RejectionReason::Trapped, 0, current_state));
+        }
+
+        let mut length = 0;
+        for (position, c) in input.into_iter().enumerate() {
+            match self.next_state(current_state, c) {
+                None => {
+                    return Some(report(
+                        RejectionReason::UnknownChar(c),
+                        position,
+                        current_state,
+                    ));
+                }
+                Some(next_state) if self.trap_states[next_state] => {
+                    return Some(report(RejectionReason::Trapped, position, current_state));
+                }
+                Some(next_state) => current_state = next_state,
+            }
+            length = position + 1;
+        }
+
+        if self.accept_states[current_state] {
+            None
+        } else {
+            Some(report(
+                RejectionReason::EndedInNonAccepting,
+                length,
+                current_state,
+            ))
+        }
+    }
+
+    /// Returns the symbols leading from `state_idx` to a state that is not a trap, in alphabet
+    /// order.
+    pub fn viable_symbols(&self, state_idx: usize) -> Vec<char> {
+        (0..self.alphabet.len())
+            .filter(|&alpha_idx| !self.trap_states[self.transition(state_idx, alpha_idx)])
+            .filter_map(|alpha_idx| self.alphabet.get_by_right(&alpha_idx).copied())
+            .collect()
+    }
+}
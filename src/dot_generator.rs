@@ -1,26 +1,40 @@
 use crate::dfa::Dfa;
 use crate::parser::Nfa;
+use crate::symbolic::{IntervalSet, SymbolicDfa};
 use anyhow::Result;
+use bimap::BiMap;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
-/// Generates a Graphviz DOT file representation of the DFA.
-pub fn make_dot(fsm: &Dfa, filename: impl AsRef<Path>) -> Result<()> {
+/// A state as drawn by [`write_dot`].
+struct DotNode<'a> {
+    key: &'a str,
+    label: String,
+    accept: bool,
+}
+
+/// Writes a Graphviz DOT file with one node per state (in index order), an arrow into the start
+/// state, and the edges leaving each state as the `(dest_idx, label)` pairs returned by `edges`.
+fn write_dot<F>(
+    filename: impl AsRef<Path>,
+    name: &str,
+    description: Option<&str>,
+    nodes: &[DotNode],
+    start_idx: usize,
+    edges: F,
+) -> Result<()>
+where
+    F: Fn(usize) -> Vec<(usize, String)>,
+{
     let mut file = File::create(filename)?;
 
-    writeln!(
-        &mut file,
-        "digraph \"{}\" {{",
-        fsm.name.replace('\"', "\\\"")
-    )?;
+    writeln!(&mut file, "digraph \"{}\" {{", name.replace('\"', "\\\""))?;
     writeln!(&mut file, "    rankdir=LR;")?;
 
-    let label = fsm
-        .description
-        .as_deref()
-        .unwrap_or(&fsm.name)
+    let label = description
+        .unwrap_or(name)
         .replace('\"', "\\\"")
         .replace('\n', "\\n");
     writeln!(&mut file, "    label=\"{}\";", label)?;
@@ -28,190 +42,195 @@ pub fn make_dot(fsm: &Dfa, filename: impl AsRef<Path>) -> Result<()> {
 
     writeln!(&mut file, "    __start [shape=none, label=\"\"];")?;
 
-    for (idx, props) in fsm.state_properties.iter().enumerate() {
-        let state_key = fsm
-            .state_keys
-            .get_by_right(&idx)
-            .unwrap_or_else(|| panic!("State index {} not found in state_keys", idx));
-
-        let shape = if fsm.accept_states[idx] {
+    for node in nodes {
+        let shape = if node.accept {
             "doublecircle"
         } else {
             "circle"
         };
-
-        let label = props
-            .label
-            .as_deref()
-            .unwrap_or(state_key)
-            .replace('\"', "\\\"");
-
         writeln!(
             &mut file,
             "    \"{}\" [label=\"{}\", shape={}];",
-            state_key.replace('\"', "\\\""),
-            label,
+            node.key.replace('\"', "\\\""),
+            node.label.replace('\"', "\\\""),
             shape
         )?;
     }
 
-    let start_key = fsm
-        .state_keys
-        .get_by_right(&fsm.start_state_idx)
-        .unwrap_or_else(|| {
-            panic!(
-                "Start state index {} not found in state_keys",
-                fsm.start_state_idx
-            )
-        });
     writeln!(
         &mut file,
         "    __start -> \"{}\";",
-        start_key.replace('\"', "\\\"")
+        nodes[start_idx].key.replace('\"', "\\\"")
     )?;
 
-    let mut transitions: BTreeMap<(usize, usize), BTreeSet<char>> = BTreeMap::new();
-
-    // transition table is now a 1d vec of size (num_states * alphabet_size)
-    let alphabet_size = fsm.alphabet.len();
-    for (src_idx, row) in fsm.transition_table.chunks(alphabet_size).enumerate() {
-        for (alpha_idx, &dest_idx) in row.iter().enumerate() {
-            let c = fsm
-                .alphabet
-                .get_by_right(&alpha_idx)
-                .unwrap_or_else(|| panic!("Alphabet index {} not found in alphabet", alpha_idx));
-            transitions
-                .entry((src_idx, dest_idx))
-                .or_default()
-                .insert(*c);
+    for (src_idx, src) in nodes.iter().enumerate() {
+        for (dest_idx, label) in edges(src_idx) {
+            writeln!(
+                &mut file,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                src.key.replace('\"', "\\\""),
+                nodes[dest_idx].key.replace('\"', "\\\""),
+                label.replace('\"', "\\\"")
+            )?;
         }
     }
 
-    for ((src_idx, dest_idx), chars) in transitions {
-        let src_key = fsm
-            .state_keys
-            .get_by_right(&src_idx)
-            .unwrap_or_else(|| panic!("State index {} not found in state_keys", src_idx));
-        let dest_key = fsm
-            .state_keys
-            .get_by_right(&dest_idx)
-            .unwrap_or_else(|| panic!("State index {} not found in state_keys", dest_idx));
-        let label = format_char_set(&chars);
-
-        writeln!(
-            &mut file,
-            "    \"{}\" -> \"{}\" [label=\"{}\"];",
-            src_key.replace('\"', "\\\""),
-            dest_key.replace('\"', "\\\""),
-            label.replace('\"', "\\\"")
-        )?;
-    }
-
     writeln!(&mut file, "}}")?;
     Ok(())
 }
 
+/// Returns the key of every state, in index order.
+fn keys_by_index(state_keys: &BiMap<String, usize>) -> Vec<&str> {
+    (0..state_keys.len())
+        .map(|idx| {
+            state_keys
+                .get_by_right(&idx)
+                .unwrap_or_else(|| panic!("State index {} not found in state_keys", idx))
+                .as_str()
+        })
+        .collect()
+}
+
+/// Generates a Graphviz DOT file representation of the DFA.
+pub fn make_dot(fsm: &Dfa, filename: impl AsRef<Path>) -> Result<()> {
+    let nodes: Vec<DotNode> = keys_by_index(&fsm.state_keys)
+        .into_iter()
+        .zip(&fsm.state_properties)
+        .enumerate()
+        .map(|(idx, (key, props))| {
+            let mut label = props.label.as_deref().unwrap_or(key).to_string();
+            // Moore outputs are shown in the state as "label / output"
+            if let Some(output) = &props.output {
+                label = format!("{} / {}", label, output);
+            }
+            DotNode {
+                key,
+                label,
+                accept: fsm.accept_states[idx],
+            }
+        })
+        .collect();
+
+    // the transition table has one column per equivalence class of symbols
+    let class_members = fsm.class_members();
+
+    write_dot(
+        filename,
+        &fsm.name,
+        fsm.description.as_deref(),
+        &nodes,
+        fsm.start_state_idx,
+        |src_idx| {
+            // (dest, Mealy output) -> symbols
+            let mut transitions: BTreeMap<(usize, Option<&str>), BTreeSet<char>> = BTreeMap::new();
+            for (class_idx, members) in class_members.iter().enumerate() {
+                transitions
+                    .entry((
+                        fsm.class_transition(src_idx, class_idx),
+                        fsm.class_transition_output(src_idx, class_idx),
+                    ))
+                    .or_default()
+                    .extend(members);
+            }
+
+            transitions
+                .into_iter()
+                .map(|((dest_idx, output), chars)| {
+                    // Mealy outputs are shown on the edge as "symbols/output", e.g. "a/1"
+                    let label = match output {
+                        Some(output) => format!("{}/{}", format_char_set(&chars), output),
+                        None => format_char_set(&chars),
+                    };
+                    (dest_idx, label)
+                })
+                .collect()
+        },
+    )
+}
+
 pub fn make_nfa_dot(
     nfa: &Nfa,
     fsm_name: &str,
     description: Option<&str>,
     filename: impl AsRef<Path>,
 ) -> Result<()> {
-    let mut file = File::create(filename)?;
-
-    writeln!(
-        &mut file,
-        "digraph \"{}\" {{",
-        fsm_name.replace('\"', "\\\"")
-    )?;
-    writeln!(&mut file, "    rankdir=LR;")?;
-
-    let label = description
-        .unwrap_or(fsm_name)
-        .replace('\"', "\\\"")
-        .replace('\n', "\\n");
-    writeln!(&mut file, "    label=\"{}\";", label)?;
-    writeln!(&mut file, "    node [shape=circle];")?;
-
-    writeln!(&mut file, "    __start [shape=none, label=\"\"];")?;
-
-    for (state_key, &idx) in nfa.nfa_state_keys.iter() {
-        let shape = if nfa.nfa_accept_states.contains(&idx) {
-            "doublecircle"
-        } else {
-            "circle"
-        };
-
-        writeln!(
-            &mut file,
-            "    \"{}\" [label=\"{}\", shape={}];",
-            state_key.replace('\"', "\\\""),
-            state_key.replace('\"', "\\\""),
-            shape
-        )?;
-    }
-
-    let start_key = nfa
-        .nfa_state_keys
-        .get_by_right(&nfa.start_state)
-        .unwrap_or_else(|| {
-            panic!(
-                "Start state index {} not found in nfa_state_keys",
-                nfa.start_state
-            )
-        });
-    writeln!(
-        &mut file,
-        "    __start -> \"{}\";",
-        start_key.replace('\"', "\\\"")
-    )?;
-
-    // group transitions by (src, dest) to consolidate labels
-    let mut transitions_grouped: BTreeMap<(usize, usize), BTreeSet<Option<char>>> = BTreeMap::new();
-    for ((src_idx, on_char), dest_states) in &nfa.transitions {
-        for &dest_idx in dest_states {
-            transitions_grouped
-                .entry((*src_idx, dest_idx))
-                .or_default()
-                .insert(*on_char);
-        }
-    }
-
-    for ((src_idx, dest_idx), chars) in transitions_grouped {
-        let src_key = nfa
-            .nfa_state_keys
-            .get_by_right(&src_idx)
-            .unwrap_or_else(|| panic!("State index {} not found in nfa_state_keys", src_idx));
-        let dest_key = nfa
-            .nfa_state_keys
-            .get_by_right(&dest_idx)
-            .unwrap_or_else(|| panic!("State index {} not found in nfa_state_keys", dest_idx));
-        let label_chars: BTreeSet<char> = chars.iter().filter_map(|&c| c).collect();
-        let mut label_parts = Vec::new();
-        if !label_chars.is_empty() {
-            label_parts.push(format_char_set(&label_chars));
-        }
-        if chars.contains(&None) {
-            label_parts.push("ε".to_string());
-        }
-
-        let label = label_parts.join(", ");
+    let nodes: Vec<DotNode> = keys_by_index(&nfa.nfa_state_keys)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, key)| DotNode {
+            key,
+            label: key.to_string(),
+            accept: nfa.nfa_accept_states.contains(&idx),
+        })
+        .collect();
+
+    write_dot(
+        filename,
+        fsm_name,
+        description,
+        &nodes,
+        nfa.start_state,
+        |src_idx| {
+            // group transitions by destination to consolidate labels
+            let mut transitions: BTreeMap<usize, BTreeSet<Option<char>>> = BTreeMap::new();
+            let from_src = nfa
+                .transitions
+                .range((src_idx, None)..=(src_idx, Some(char::MAX)));
+            for (&(_, on_char), dest_states) in from_src {
+                for &dest_idx in dest_states {
+                    transitions.entry(dest_idx).or_default().insert(on_char);
+                }
+            }
 
-        writeln!(
-            &mut file,
-            "    \"{}\" -> \"{}\" [label=\"{}\"];",
-            src_key.replace('\"', "\\\""),
-            dest_key.replace('\"', "\\\""),
-            label.replace('\"', "\\\"")
-        )?;
-    }
+            transitions
+                .into_iter()
+                .map(|(dest_idx, chars)| {
+                    let label_chars: BTreeSet<char> = chars.iter().filter_map(|&c| c).collect();
+                    let mut label_parts = Vec::new();
+                    if !label_chars.is_empty() {
+                        label_parts.push(format_char_set(&label_chars));
+                    }
+                    if chars.contains(&None) {
+                        label_parts.push("ε".to_string());
+                    }
+                    (dest_idx, label_parts.join(", "))
+                })
+                .collect()
+        },
+    )
+}
 
-    writeln!(&mut file, "}}")?;
-    Ok(())
+/// Generates a Graphviz DOT file representation of a symbolic DFA, labeling each edge with the
+/// character ranges it is taken on.
+pub fn make_symbolic_dot(fsm: &SymbolicDfa, filename: impl AsRef<Path>) -> Result<()> {
+    let nodes: Vec<DotNode> = keys_by_index(&fsm.state_keys)
+        .into_iter()
+        .zip(&fsm.state_properties)
+        .enumerate()
+        .map(|(idx, (key, props))| DotNode {
+            key,
+            label: props.label.as_deref().unwrap_or(key).to_string(),
+            accept: fsm.accept_states[idx],
+        })
+        .collect();
+
+    write_dot(
+        filename,
+        &fsm.name,
+        fsm.description.as_deref(),
+        &nodes,
+        fsm.start_state_idx,
+        |src_idx| {
+            fsm.edges(src_idx)
+                .into_iter()
+                .map(|(dest_idx, symbols)| (dest_idx, format_interval_set(&symbols)))
+                .collect()
+        },
+    )
 }
 
 /// Formats a set of characters into a compact, readable string (e.g., "a-c, z, 0-9").
-fn format_char_set(chars: &BTreeSet<char>) -> String {
+pub(crate) fn format_char_set(chars: &BTreeSet<char>) -> String {
     if chars.is_empty() {
         return " ".to_string();
     }
@@ -244,6 +263,26 @@ fn format_char_set(chars: &BTreeSet<char>) -> String {
     parts.join(", ")
 }
 
+/// Formats the ranges of an interval set like [`format_char_set`] (e.g., "a-c, z, U+0080-U+10FFFF").
+fn format_interval_set(symbols: &IntervalSet) -> String {
+    if symbols.is_empty() {
+        return " ".to_string();
+    }
+
+    let parts: Vec<String> = symbols
+        .ranges()
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                format_char(start)
+            } else {
+                format!("{}-{}", format_char(start), format_char(end))
+            }
+        })
+        .collect();
+    parts.join(", ")
+}
+
 /// Formats a single character for display, escaping special DOT characters.
 fn format_char(c: char) -> String {
     match c {
@@ -252,6 +291,8 @@ fn format_char(c: char) -> String {
         ' ' => "␣".to_string(),
         ',' => "[comma]".to_string(),
         '-' => "[dash]".to_string(),
+        // control characters, and characters outside the Basic Multilingual Plane that fonts rarely cover
+        c if c.is_control() || c as u32 > 0xFFFF => format!("U+{:04X}", c as u32),
         _ => c.to_string(),
     }
 }
@@ -0,0 +1,118 @@
+use crate::dfa::Dfa;
+
+/// An iterator over the strings accepted (or rejected) by a DFA, in shortlex order.
+///
+/// Strings are produced shortest first, and strings of equal length are ordered by alphabet
+/// index. Only strings over the DFA's alphabet of at most `max_len` characters are produced; use
+/// [`Iterator::take`] to bound the count as well.
+pub struct ShortlexStrings<'a> {
+    dfa: &'a Dfa,
+    /// alphabet index -> char
+    symbols: Vec<char>,
+    max_len: usize,
+    // viable[k][state] is true if some string of exactly k characters leads from state to a target
+    viable: Vec<Vec<bool>>,
+    /// Length of the strings currently being produced.
+    length: usize,
+    /// Next length to start once the current one is exhausted.
+    next_length: usize,
+    // depth-first search frames: (state after the first i chars, next alphabet index to try)
+    stack: Vec<(usize, usize)>,
+    current: Vec<char>,
+}
+
+impl<'a> ShortlexStrings<'a> {
+    fn new(dfa: &'a Dfa, max_len: usize, accepting: bool) -> Self {
+        let alphabet_size = dfa.alphabet.len();
+        let state_count = dfa.state_keys.len();
+
+        let mut symbols = vec!['\0'; alphabet_size];
+        for (&c, &idx) in dfa.alphabet.iter() {
+            symbols[idx] = c;
+        }
+
+        let mut viable: Vec<Vec<bool>> = Vec::with_capacity(max_len + 1);
+        viable.push(
+            dfa.accept_states
+                .iter()
+                .map(|&accept| accept == accepting)
+                .collect(),
+        );
+        for k in 1..=max_len {
+            let row = (0..state_count)
+                .map(|s| (0..alphabet_size).any(|a| viable[k - 1][dfa.transition(s, a)]))
+                .collect();
+            viable.push(row);
+        }
+
+        ShortlexStrings {
+            dfa,
+            symbols,
+            max_len,
+            viable,
+            length: 0,
+            next_length: 0,
+            stack: Vec::new(),
+            current: Vec::new(),
+        }
+    }
+}
+
+impl Iterator for ShortlexStrings<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            if self.stack.is_empty() {
+                // move on to the next length that has at least one target string
+                if self.next_length > self.max_len {
+                    return None;
+                }
+                self.length = self.next_length;
+                self.next_length += 1;
+                if self.viable[self.length][self.dfa.start_state_idx] {
+                    self.stack.push((self.dfa.start_state_idx, 0));
+                    self.current.clear();
+                }
+                continue;
+            }
+
+            // only viable paths are explored, so a full-length path always ends in a target
+            if self.current.len() == self.length {
+                let result = self.current.iter().collect();
+                self.stack.pop();
+                self.current.pop();
+                return Some(result);
+            }
+
+            let remaining = self.length - self.current.len() - 1;
+            let (state, alpha_idx) = self.stack.last_mut().expect("stack is not empty");
+            if *alpha_idx < self.symbols.len() {
+                let symbol_idx = *alpha_idx;
+                *alpha_idx += 1;
+                let dest = self.dfa.transition(*state, symbol_idx);
+                if self.viable[remaining][dest] {
+                    self.stack.push((dest, 0));
+                    self.current.push(self.symbols[symbol_idx]);
+                }
+            } else {
+                self.stack.pop();
+                self.current.pop();
+            }
+        }
+    }
+}
+
+impl Dfa {
+    /// Returns an iterator over the accepted strings of at most `max_len` characters, in shortlex
+    /// order.
+    pub fn accepted_strings(&self, max_len: usize) -> ShortlexStrings<'_> {
+        ShortlexStrings::new(self, max_len, true)
+    }
+
+    /// Returns an iterator over the rejected strings over the alphabet of at most `max_len`
+    /// characters, in shortlex order.
+    pub fn rejected_strings(&self, max_len: usize) -> ShortlexStrings<'_> {
+        ShortlexStrings::new(self, max_len, false)
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::analysis;
+use crate::parser::{self, Nfa};
+
+/// Runs an NFA through on-the-fly subset construction, without building the whole DFA.
+///
+/// DFA states (sets of NFA states) and their transitions are only computed when an input reaches
+/// them, and are cached for later runs. The cache holds at most `max_cached_states` DFA states;
+/// when it is full it is cleared and rebuilt from the states in use, so memory stays bounded even
+/// for NFAs whose full DFA would be exponentially large.
+#[derive(Debug, Clone)]
+pub struct LazyDfa {
+    pub name: String,
+    pub description: Option<String>,
+    pub nfa: Nfa,
+    max_cached_states: usize,
+    /// NFA states from which an accept state is reachable; other states are dropped from every
+    /// set, so that a set becoming empty means the input can no longer be accepted.
+    useful: Vec<bool>,
+    /// cached state index -> set of NFA states
+    states: Vec<BTreeSet<usize>>,
+    state_ids: HashMap<BTreeSet<usize>, usize>,
+    accepting: Vec<bool>,
+    // (cached state index, char) -> cached state index
+    transitions: HashMap<(usize, char), usize>,
+    cache_clears: usize,
+}
+
+impl LazyDfa {
+    /// Creates a lazy DFA for `nfa`, caching at most `max_cached_states` DFA states (at least 2
+    /// are always allowed, for the current state and the next one).
+    pub fn new(
+        name: String,
+        description: Option<String>,
+        nfa: Nfa,
+        max_cached_states: usize,
+    ) -> LazyDfa {
+        let useful = useful_states(&nfa);
+        LazyDfa {
+            name,
+            description,
+            nfa,
+            max_cached_states: max_cached_states.max(2),
+            useful,
+            states: Vec::new(),
+            state_ids: HashMap::new(),
+            accepting: Vec::new(),
+            transitions: HashMap::new(),
+            cache_clears: 0,
+        }
+    }
+
+    /// Parses an NFA spec from a YAML string for lazy determinization.
+    pub fn from_yaml(yaml_content: &str, max_cached_states: usize) -> Result<LazyDfa> {
+        parser::lazy_from_yaml(yaml_content, max_cached_states)
+    }
+
+    /// Runs the NFA on the given input string and returns true if accepted, false otherwise.
+    ///
+    /// Stops early once no NFA state that can still reach an accept state is active.
+    pub fn run<I>(&mut self, input: I) -> bool
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let start = self.closure(&BTreeSet::from([self.nfa.start_state]));
+        if !self.state_ids.contains_key(&start) && self.states.len() >= self.max_cached_states {
+            self.clear_cache();
+        }
+        let mut current = self.state_id(start);
+
+        for c in input {
+            current = match self.transitions.get(&(current, c)) {
+                Some(&next) => next,
+                None => self.compute_transition(current, c),
+            };
+            if self.states[current].is_empty() {
+                return false;
+            }
+        }
+
+        self.accepting[current]
+    }
+
+    /// Returns the number of DFA states currently cached.
+    pub fn cached_states(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns how many times the cache was cleared because it was full.
+    pub fn cache_clears(&self) -> usize {
+        self.cache_clears
+    }
+
+    /// Computes, caches and returns the successor of a cached state on `c`.
+    ///
+    /// May clear the cache, in which case the returned index refers to the rebuilt cache.
+    fn compute_transition(&mut self, current: usize, c: char) -> usize {
+        let moved = self.nfa.move_on_char(&self.states[current], c);
+        let next_set = self.closure(&moved);
+
+        let (current, next) = if self.state_ids.contains_key(&next_set)
+            || self.states.len() < self.max_cached_states
+        {
+            (current, self.state_id(next_set))
+        } else {
+            // the cache is full: start over, keeping only the states involved in this step
+            let current_set = self.states[current].clone();
+            self.clear_cache();
+            let current = self.state_id(current_set);
+            (current, self.state_id(next_set))
+        };
+
+        self.transitions.insert((current, c), next);
+        next
+    }
+
+    /// Returns the cached index of a set of NFA states, adding it to the cache if needed.
+    fn state_id(&mut self, set: BTreeSet<usize>) -> usize {
+        if let Some(&id) = self.state_ids.get(&set) {
+            return id;
+        }
+        let id = self.states.len();
+        let accepting = set
+            .intersection(&self.nfa.nfa_accept_states)
+            .next()
+            .is_some();
+        self.state_ids.insert(set.clone(), id);
+        self.states.push(set);
+        self.accepting.push(accepting);
+        id
+    }
+
+    /// Returns the epsilon closure of `states`, without states that cannot reach an accept state.
+    fn closure(&self, states: &BTreeSet<usize>) -> BTreeSet<usize> {
+        self.nfa
+            .epsilon_closure(states)
+            .into_iter()
+            .filter(|&s| self.useful[s])
+            .collect()
+    }
+
+    fn clear_cache(&mut self) {
+        self.states.clear();
+        self.state_ids.clear();
+        self.accepting.clear();
+        self.transitions.clear();
+        self.cache_clears += 1;
+    }
+}
+
+/// Returns, for every NFA state, whether an accept state is reachable from it.
+fn useful_states(nfa: &Nfa) -> Vec<bool> {
+    let mut accepting = vec![false; nfa.nfa_state_keys.len()];
+    for &state in &nfa.nfa_accept_states {
+        accepting[state] = true;
+    }
+    analysis::coreachable(&accepting, |src| {
+        nfa.transitions
+            .range((src, None)..=(src, Some(char::MAX)))
+            .flat_map(|(_, dests)| dests.iter().cloned())
+    })
+}
@@ -0,0 +1,120 @@
+use anyhow::{Result, anyhow};
+
+use crate::dfa::Dfa;
+
+/// A token produced by a [`Lexer`].
+///
+/// Spans are half-open and given both in bytes (for slicing the input) and in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'d, 't> {
+    /// The token name, from the `token` of the accept state the match ended in.
+    pub kind: &'d str,
+    pub text: &'t str,
+    pub start: usize,
+    pub end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+/// Splits an input into tokens by maximal munch.
+///
+/// Starting at the beginning of the input, the DFA is run as far as it can go and the longest
+/// prefix ending in an accept state becomes the next token, named after that state's `token`.
+/// Lexing then resumes right after it. If no non-empty prefix is accepted, the iterator yields an
+/// error and stops.
+pub struct Lexer<'d, 't> {
+    dfa: &'d Dfa,
+    input: &'t str,
+    /// Byte offset of the next token.
+    pos: usize,
+    /// Character offset corresponding to `pos`.
+    char_pos: usize,
+    failed: bool,
+}
+
+impl<'d, 't> Lexer<'d, 't> {
+    /// Creates a lexer for `input`.
+    ///
+    /// Fails if some accept state of the DFA has no token, since matches ending there could not
+    /// be named.
+    pub fn new(dfa: &'d Dfa, input: &'t str) -> Result<Self> {
+        if let Some(idx) = (0..dfa.state_keys.len())
+            .find(|&s| dfa.accept_states[s] && dfa.state_properties[s].token.is_none())
+        {
+            return Err(anyhow!(
+                "Accept state '{}' has no token",
+                dfa.state_key(idx)
+            ));
+        }
+
+        Ok(Lexer {
+            dfa,
+            input,
+            pos: 0,
+            char_pos: 0,
+            failed: false,
+        })
+    }
+}
+
+impl<'d, 't> Iterator for Lexer<'d, 't> {
+    type Item = Result<Token<'d, 't>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pos >= self.input.len() {
+            return None;
+        }
+
+        let dfa = self.dfa;
+        let rest = &self.input[self.pos..];
+        let mut state = dfa.start_state_idx;
+        // (byte length, char length, accept state) of the longest accepted prefix so far
+        let mut longest: Option<(usize, usize, usize)> = None;
+
+        for (char_len, (byte_offset, c)) in rest.char_indices().enumerate() {
+            let Some(next_state) = dfa.next_state(state, c) else {
+                break;
+            };
+            state = next_state;
+            if dfa.trap_states[state] {
+                break;
+            }
+            if dfa.accept_states[state] {
+                longest = Some((byte_offset + c.len_utf8(), char_len + 1, state));
+            }
+        }
+
+        let Some((byte_len, char_len, accept_state)) = longest else {
+            self.failed = true;
+            let c = rest.chars().next().unwrap_or_default();
+            return Some(Err(anyhow!(
+                "No token matches at position {} ({:?})",
+                self.char_pos,
+                c
+            )));
+        };
+
+        let token = Token {
+            kind: dfa.state_properties[accept_state]
+                .token
+                .as_deref()
+                .unwrap_or_default(),
+            text: &rest[..byte_len],
+            start: self.pos,
+            end: self.pos + byte_len,
+            char_start: self.char_pos,
+            char_end: self.char_pos + char_len,
+        };
+        self.pos += byte_len;
+        self.char_pos += char_len;
+
+        Some(Ok(token))
+    }
+}
+
+impl Dfa {
+    /// Returns a lexer splitting `input` into the tokens recognized by the DFA.
+    pub fn tokenize<'d, 't>(&'d self, input: &'t str) -> Result<Lexer<'d, 't>> {
+        Lexer::new(self, input)
+    }
+}
@@ -1,3 +1,21 @@
+pub mod analysis;
+pub mod boolean;
+pub mod classes;
+pub mod compare;
+pub mod counting;
 pub mod dfa;
+pub mod diagnostics;
 pub mod dot_generator;
+pub mod enumerate;
+pub mod lazy;
+pub mod lexer;
+pub mod minimize;
 pub mod parser;
+pub mod regex;
+pub mod runner;
+pub mod sample;
+pub mod search;
+pub mod state_elimination;
+pub mod symbolic;
+pub mod transducer;
+pub mod utf8;
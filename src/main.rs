@@ -1,21 +1,47 @@
-use anyhow::Result;
-use clap::Parser;
-use fsm::parser::Fsm;
+use anyhow::{Result, anyhow};
+use clap::{Parser, Subcommand};
+use fsm::compare::{Equivalence, Inclusion, Machine};
+use fsm::dfa::Dfa;
+use fsm::diagnostics::RejectionReason;
+use fsm::lazy::LazyDfa;
+use fsm::parser::{DeterminizeLimits, Fsm};
+use fsm::regex::Regex;
+use fsm::search::MatchKind;
+use fsm::symbolic::SymbolicDfa;
+use fsm::transducer::Transducer;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use rustyline::Editor;
 use rustyline::error::ReadlineError;
 use rustyline::history::FileHistory;
+use std::collections::BTreeSet;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 /// A command-line tool for loading and running Deterministic Finite Automata (DFA)
 /// from YAML specifications.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The path to the .yml DFA specification file.
-    #[arg(required = true)]
-    file: PathBuf,
+    #[arg(required_unless_present = "regex", conflicts_with = "regex")]
+    file: Option<PathBuf>,
+
+    /// Compile a regular expression instead of loading a specification file.
+    #[arg(long, value_name = "PATTERN")]
+    regex: Option<String>,
+
+    /// Extra alphabet characters for --regex, written like the inside of a character class
+    /// (e.g. 'a-z0-9'). By default the alphabet is the set of characters in the pattern.
+    #[arg(long, value_name = "CHARS", requires = "regex")]
+    alphabet: Option<String>,
 
     /// Generate a Graphviz DOT file for visualization.
     #[arg(long)]
@@ -24,49 +50,236 @@ struct Args {
     /// Print the transition table to the console.
     #[arg(long)]
     table: bool,
+
+    /// Minimize the DFA before printing, visualizing or running it.
+    #[arg(long)]
+    minimize: bool,
+
+    /// Replace the FSM with its complement before printing, visualizing or running it.
+    /// NFAs are complemented through their determinized DFA.
+    #[arg(long)]
+    complement: bool,
+
+    /// Explain why each input is rejected in the REPL. Can be toggled with the 'verbose' command.
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Run an NFA in the REPL by determinizing it on the fly, instead of building the whole DFA
+    /// up front.
+    #[arg(long, conflicts_with_all = ["regex", "table", "viz", "minimize", "complement", "verbose"])]
+    lazy: bool,
+
+    /// Load the spec as a symbolic automaton, whose transitions are character ranges, for
+    /// alphabets too large to list character by character (e.g. all of Unicode).
+    #[arg(long, conflicts_with_all = ["regex", "table", "minimize", "complement", "verbose", "lazy"])]
+    symbolic: bool,
+
+    /// The maximum number of DFA states cached by --lazy before the cache is cleared.
+    #[arg(long, value_name = "STATES", default_value_t = 1024, requires = "lazy")]
+    cache_states: usize,
+
+    /// Fail if determinizing an NFA spec creates more DFA states than this.
+    #[arg(long, value_name = "STATES", global = true, default_value_t = 100_000)]
+    max_states: usize,
+
+    /// Fail if determinizing an NFA spec creates a transition table with more cells (DFA states
+    /// times alphabet size) than this.
+    #[arg(
+        long,
+        value_name = "CELLS",
+        global = true,
+        default_value_t = 10_000_000
+    )]
+    max_cells: usize,
 }
 
-fn main() {
-    if let Err(e) = run_cli() {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+/// Subcommands that operate on one or more FSM-YAML files without starting the REPL.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print a report of an FSM's size and of the properties of its language.
+    Info {
+        /// The .yml specification file.
+        file: PathBuf,
+    },
+    /// Print the number of accepted strings of each length.
+    Count {
+        /// The .yml specification file.
+        file: PathBuf,
+        /// The maximum string length to count.
+        #[arg(long, default_value_t = 10)]
+        max_len: usize,
+    },
+    /// Print accepted strings of a given length, drawn uniformly at random.
+    Sample {
+        /// The .yml specification file.
+        file: PathBuf,
+        /// The length of the strings to generate.
+        #[arg(long)]
+        len: usize,
+        /// The number of strings to generate.
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+        /// Seed for the random number generator, for reproducible output.
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Print a regular expression equivalent to an FSM, obtained by state elimination.
+    ToRegex {
+        /// The .yml specification file.
+        file: PathBuf,
+        /// Eliminate states of the NFA as written, instead of the minimized DFA.
+        #[arg(long)]
+        nfa: bool,
+    },
+    /// Print the lines of a text file that contain a substring in an FSM's language.
+    ///
+    /// Each matching line is printed with its number and the character spans of the matches.
+    /// Exits with 0 if a match was found, 1 if none was and 2 on error.
+    Grep {
+        /// The .yml specification file.
+        file: PathBuf,
+        /// The text file to search.
+        input: PathBuf,
+        /// Report the first (i.e. shortest) match at each position instead of the longest.
+        #[arg(long)]
+        first: bool,
+    },
+    /// Split standard input into tokens by maximal munch and print the token stream.
+    ///
+    /// Every accept state of the FSM must name the token it recognizes.
+    Lex {
+        /// The .yml specification file.
+        file: PathBuf,
+    },
+    /// Print every output of a transducer for an input string.
+    ///
+    /// Exits with 0 if there is at least one output, 1 if there is none and 2 on error.
+    Transduce {
+        /// The .yml transducer specification file.
+        file: PathBuf,
+        /// The input string.
+        input: String,
+        /// Transducers to run on the output, in order (composing them with the first one).
+        #[arg(long, value_name = "FILE")]
+        then: Vec<PathBuf>,
+    },
+    /// Check whether two FSMs accept the same language.
+    ///
+    /// Exits with 0 if they are equivalent, 1 if they differ and 2 on error.
+    Equiv {
+        /// The first .yml specification file.
+        first: PathBuf,
+        /// The second .yml specification file.
+        second: PathBuf,
+    },
+    /// Check whether every string accepted by one FSM is accepted by another.
+    ///
+    /// Exits with 0 if the inclusion holds, 1 if it does not and 2 on error.
+    Subset {
+        /// The .yml specification file of the (stricter) machine that should be contained.
+        subset: PathBuf,
+        /// The .yml specification file of the (looser) machine that should contain it.
+        superset: PathBuf,
+    },
+}
+
+impl Command {
+    /// Returns the exit code used when the command fails. Commands that report their result with
+    /// exit codes 0 and 1 use 2 for errors; all others exit with 1, like the REPL.
+    fn error_exit_code(&self) -> u8 {
+        match self {
+            Command::Grep { .. }
+            | Command::Transduce { .. }
+            | Command::Equiv { .. }
+            | Command::Subset { .. } => 2,
+            _ => 1,
+        }
     }
 }
 
-/// The main CLI logic, handling argument parsing, FSM loading, and REPL.
-fn run_cli() -> Result<()> {
+fn main() -> ExitCode {
     let args = Args::parse();
+    let error_exit_code = args.command.as_ref().map_or(1, Command::error_exit_code);
+    match run_cli(args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(error_exit_code)
+        }
+    }
+}
 
-    let mut fsm = load_fsm(&args.file)?;
-    let mut current_path = args.file.clone();
+/// The main CLI logic, handling FSM loading and the REPL.
+fn run_cli(args: Args) -> Result<ExitCode> {
+    let limits = DeterminizeLimits {
+        max_states: Some(args.max_states),
+        max_cells: Some(args.max_cells),
+    };
 
-    if args.table {
-        match &fsm {
-            Fsm::Dfa(dfa) => dfa.print_transition_table(),
-            Fsm::Nfa { dfa, .. } => dfa.print_transition_table(),
+    if let Some(command) = &args.command {
+        return match command {
+            Command::Info { file } => run_info(file, &limits),
+            Command::Count { file, max_len } => run_count(file, *max_len, &limits),
+            Command::Sample {
+                file,
+                len,
+                count,
+                seed,
+            } => run_sample(file, *len, *count, *seed, &limits),
+            Command::ToRegex { file, nfa } => run_to_regex(file, *nfa, &limits),
+            Command::Grep { file, input, first } => run_grep(file, input, *first, &limits),
+            Command::Lex { file } => run_lex(file, &limits),
+            Command::Transduce { file, input, then } => run_transduce(file, input, then),
+            Command::Equiv { first, second } => run_equiv(first, second, &limits),
+            Command::Subset { subset, superset } => run_subset(subset, superset, &limits),
+        };
+    }
+
+    if args.lazy {
+        // clap guarantees that the file is given, since --lazy conflicts with --regex
+        let path = args.file.as_deref().expect("--lazy requires a file");
+        return run_lazy_repl(path, args.cache_states);
+    }
+
+    if args.symbolic {
+        // clap guarantees that the file is given, since --symbolic conflicts with --regex
+        let path = args.file.as_deref().expect("--symbolic requires a file");
+        if args.viz {
+            let contents = std::fs::read_to_string(path)?;
+            let dfa = fsm::parser::symbolic_from_yaml(&contents, &limits)?;
+            fsm::dot_generator::make_symbolic_dot(&dfa, path.with_extension("dot"))?;
+            generate_and_print_viz_instructions(path, "")?;
+            return Ok(ExitCode::SUCCESS);
         }
+        return run_symbolic_repl(path, &limits);
+    }
+
+    // clap guarantees that exactly one of the file and the regex is given without a subcommand
+    let mut source = match (&args.file, &args.regex) {
+        (Some(file), _) => Source::File(file.clone()),
+        (None, Some(pattern)) => Source::Regex(pattern.clone()),
+        (None, None) => unreachable!("either a file or --regex is required"),
+    };
+    let mut fsm = prepare_fsm(load_source(&source, &args, &limits)?, &args);
+    let mut verbose = args.verbose;
+
+    if args.table {
+        fsm.dfa().print_transition_table();
     } else if args.viz {
-        run_viz(&fsm, &current_path)?;
+        run_viz(&fsm, &source.viz_path())?;
     } else {
         println!(
             "Loading DFA with {} states and {} transitions...",
-            match &fsm {
-                Fsm::Dfa(dfa) => dfa.state_keys.len(),
-                Fsm::Nfa { dfa, .. } => dfa.state_keys.len(),
-            },
-            match &fsm {
-                Fsm::Dfa(dfa) => dfa.transition_table.len(),
-                Fsm::Nfa { dfa, .. } => dfa.transition_table.len(),
-            },
+            fsm.dfa().state_keys.len(),
+            fsm.dfa().state_keys.len() * fsm.dfa().alphabet.len(),
         );
         println!(
             "FSM '{}' loaded. (Press Ctrl+C or type 'exit' to quit)",
-            match &fsm {
-                Fsm::Dfa(dfa) => &dfa.name,
-                Fsm::Nfa { dfa, .. } => &dfa.name,
-            }
+            fsm.dfa().name
+        );
+        println!(
+            "Commands: 'exit', 'reload', 'load <file.yml>', 'examples [n] [maxlen]', 'trace <input>', 'verbose'"
         );
-        println!("Commands: 'exit', 'reload', 'load <file.yml>'");
 
         let mut rl = Editor::<(), FileHistory>::new()?;
 
@@ -81,18 +294,16 @@ fn run_cli() -> Result<()> {
 
                     match input {
                         "exit" | "quit" => break,
+                        "verbose" => {
+                            verbose = !verbose;
+                            println!("Verbose mode {}.", if verbose { "on" } else { "off" });
+                        }
                         "reload" => {
-                            println!("Reloading '{}'...", current_path.display());
-                            match load_fsm(&current_path) {
+                            println!("Reloading '{}'...", source);
+                            match load_source(&source, &args, &limits) {
                                 Ok(new_fsm) => {
-                                    fsm = new_fsm;
-                                    println!(
-                                        "FSM '{}' reloaded successfully.",
-                                        match &fsm {
-                                            Fsm::Dfa(dfa) => &dfa.name,
-                                            Fsm::Nfa { dfa, .. } => &dfa.name,
-                                        }
-                                    );
+                                    fsm = prepare_fsm(new_fsm, &args);
+                                    println!("FSM '{}' reloaded successfully.", fsm.dfa().name);
                                 }
                                 Err(e) => eprintln!("Failed to reload: {}", e),
                             }
@@ -101,17 +312,11 @@ fn run_cli() -> Result<()> {
                             if let Some(path_str) = input.strip_prefix("load ").map(str::trim) {
                                 let new_path = PathBuf::from(path_str);
                                 println!("Loading '{}'...", new_path.display());
-                                match load_fsm(&new_path) {
+                                match load_fsm(&new_path, &limits) {
                                     Ok(new_fsm) => {
-                                        fsm = new_fsm;
-                                        current_path = new_path;
-                                        println!(
-                                            "FSM '{}' loaded successfully.",
-                                            match &fsm {
-                                                Fsm::Dfa(dfa) => &dfa.name,
-                                                Fsm::Nfa { dfa, .. } => &dfa.name,
-                                            }
-                                        );
+                                        fsm = prepare_fsm(new_fsm, &args);
+                                        source = Source::File(new_path);
+                                        println!("FSM '{}' loaded successfully.", fsm.dfa().name);
                                     }
                                     Err(e) => eprintln!("Failed to load: {}", e),
                                 }
@@ -119,11 +324,22 @@ fn run_cli() -> Result<()> {
                                 eprintln!("Invalid load command. Use: load <file.yml>");
                             }
                         }
+                        _ if input == "examples" || input.starts_with("examples ") => {
+                            let options = input.strip_prefix("examples").unwrap_or_default();
+                            if let Err(e) = print_examples(fsm.dfa(), options) {
+                                eprintln!("{}", e);
+                                eprintln!("Invalid examples command. Use: examples [n] [maxlen]");
+                            }
+                        }
+                        _ if input == "trace" || input.starts_with("trace ") => {
+                            // only a single separating space is stripped so the input may start
+                            // with whitespace
+                            let trace_input = input.strip_prefix("trace").unwrap_or_default();
+                            let trace_input = trace_input.strip_prefix(' ').unwrap_or(trace_input);
+                            print_trace(fsm.dfa(), trace_input);
+                        }
                         _ => {
-                            let dfa = match &fsm {
-                                Fsm::Dfa(dfa) => dfa,
-                                Fsm::Nfa { dfa, .. } => dfa,
-                            };
+                            let dfa = fsm.dfa();
                             let start_time = std::time::Instant::now();
                             let accepted = dfa.run(input.chars());
                             let duration = start_time.elapsed();
@@ -132,6 +348,15 @@ fn run_cli() -> Result<()> {
                                 if accepted { "ACCEPT" } else { "REJECT" },
                                 duration
                             );
+                            if dfa.has_outputs() {
+                                match dfa.transduce(input.chars()) {
+                                    Ok(outputs) => println!("OUTPUT: {}", outputs.join(" ")),
+                                    Err(e) => eprintln!("No output: {}", e),
+                                }
+                            }
+                            if verbose && !accepted {
+                                print_rejection(dfa, input);
+                            }
                         }
                     }
                 }
@@ -152,19 +377,565 @@ fn run_cli() -> Result<()> {
             }
         }
     }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Helper function to run the REPL on a symbolic automaton.
+///
+/// Only running inputs, 'reload' and 'exit' are supported.
+fn run_symbolic_repl(path: &Path, limits: &DeterminizeLimits) -> Result<ExitCode> {
+    let load = |path: &Path| -> Result<SymbolicDfa> {
+        let contents = std::fs::read_to_string(path)?;
+        fsm::parser::symbolic_from_yaml(&contents, limits)
+    };
+    let mut dfa = load(path)?;
+
+    println!(
+        "Loading symbolic DFA with {} states and {} range transitions...",
+        dfa.state_keys.len(),
+        dfa.transition_count()
+    );
+    println!(
+        "FSM '{}' loaded. (Press Ctrl+C or type 'exit' to quit)",
+        dfa.name
+    );
+    println!("Commands: 'exit', 'reload'");
+
+    let mut rl = Editor::<(), FileHistory>::new()?;
+
+    loop {
+        match rl.readline(">> ") {
+            Ok(line) => {
+                let input = line.trim();
+                if !input.is_empty() {
+                    rl.add_history_entry(input)?;
+                }
+
+                match input {
+                    "exit" | "quit" => break,
+                    "reload" => {
+                        println!("Reloading '{}'...", path.display());
+                        match load(path) {
+                            Ok(new_dfa) => {
+                                dfa = new_dfa;
+                                println!("FSM '{}' reloaded successfully.", dfa.name);
+                            }
+                            Err(e) => eprintln!("Failed to reload: {}", e),
+                        }
+                    }
+                    _ => {
+                        let start_time = std::time::Instant::now();
+                        let accepted = dfa.run(input.chars());
+                        let duration = start_time.elapsed();
+                        println!(
+                            "{} | Processed in: {:.2?}",
+                            if accepted { "ACCEPT" } else { "REJECT" },
+                            duration
+                        );
+                    }
+                }
+            }
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+                println!("Exiting.");
+                break;
+            }
+            Err(err) => {
+                eprintln!("REPL Error: {:?}", err);
+                break;
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Helper function to run the REPL on a lazily determinized NFA.
+///
+/// Only running inputs, 'reload' and 'exit' are supported, since every other command needs the
+/// full DFA.
+fn run_lazy_repl(path: &Path, cache_states: usize) -> Result<ExitCode> {
+    let load = |path: &Path| -> Result<LazyDfa> {
+        let contents = std::fs::read_to_string(path)?;
+        fsm::parser::lazy_from_yaml(&contents, cache_states)
+    };
+    let mut lazy = load(path)?;
+
+    println!(
+        "Loaded NFA with {} states for lazy determinization (caching up to {} DFA states)...",
+        lazy.nfa.nfa_state_keys.len(),
+        cache_states
+    );
+    println!(
+        "FSM '{}' loaded. (Press Ctrl+C or type 'exit' to quit)",
+        lazy.name
+    );
+    println!("Commands: 'exit', 'reload'");
+
+    let mut rl = Editor::<(), FileHistory>::new()?;
+
+    loop {
+        match rl.readline(">> ") {
+            Ok(line) => {
+                let input = line.trim();
+                if !input.is_empty() {
+                    rl.add_history_entry(input)?;
+                }
+
+                match input {
+                    "exit" | "quit" => break,
+                    "reload" => {
+                        println!("Reloading '{}'...", path.display());
+                        match load(path) {
+                            Ok(new_lazy) => {
+                                lazy = new_lazy;
+                                println!("FSM '{}' reloaded successfully.", lazy.name);
+                            }
+                            Err(e) => eprintln!("Failed to reload: {}", e),
+                        }
+                    }
+                    _ => {
+                        let start_time = std::time::Instant::now();
+                        let accepted = lazy.run(input.chars());
+                        let duration = start_time.elapsed();
+                        println!(
+                            "{} | Processed in: {:.2?} | Cached DFA states: {} | Cache clears: {}",
+                            if accepted { "ACCEPT" } else { "REJECT" },
+                            duration,
+                            lazy.cached_states(),
+                            lazy.cache_clears()
+                        );
+                    }
+                }
+            }
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+                println!("Exiting.");
+                break;
+            }
+            Err(err) => {
+                eprintln!("REPL Error: {:?}", err);
+                break;
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Helper function to print accepted strings in shortlex order for the REPL `examples` command.
+fn print_examples(dfa: &Dfa, options: &str) -> Result<()> {
+    const DEFAULT_COUNT: usize = 10;
+    const DEFAULT_MAX_LEN: usize = 10;
+
+    let mut parts = options.split_whitespace();
+    let count = parts.next().map_or(Ok(DEFAULT_COUNT), str::parse)?;
+    let max_len = parts.next().map_or(Ok(DEFAULT_MAX_LEN), str::parse)?;
+    if parts.next().is_some() {
+        return Err(anyhow!("Too many arguments"));
+    }
+
+    let mut found = 0;
+    for s in dfa.accepted_strings(max_len).take(count) {
+        println!("{:?}", s);
+        found += 1;
+    }
+    if found == 0 {
+        println!("No accepted strings of length <= {}", max_len);
+    }
+
     Ok(())
 }
 
+/// Helper function to print the path taken on an input for the REPL `trace` command.
+fn print_trace(dfa: &Dfa, input: &str) {
+    let trace = dfa.run_with_trace(input.chars());
+    let state_idx = |key: &str| dfa.state_keys.get_by_left(key).copied();
+    let state_name = |key: &str| {
+        let props = state_idx(key).map(|idx| &dfa.state_properties[idx]);
+        let mut name = match props.and_then(|p| p.label.as_deref()) {
+            Some(label) => format!("{} ({})", key, label),
+            None => key.to_string(),
+        };
+        if let Some(output) = props.and_then(|p| p.output.as_deref()) {
+            name = format!("{} / {}", name, output);
+        }
+        name
+    };
+
+    println!("    {}", state_name(trace.start));
+    for step in &trace.steps {
+        let output = state_idx(step.from)
+            .zip(dfa.alphabet.get_by_left(&step.symbol))
+            .and_then(|(src, &alpha_idx)| dfa.transition_output(src, alpha_idx));
+        match output {
+            Some(output) => {
+                println!(
+                    "  --{:?}/{}--> {}",
+                    step.symbol,
+                    output,
+                    state_name(step.to)
+                );
+            }
+            None => println!("  --{:?}--> {}", step.symbol, state_name(step.to)),
+        }
+    }
+    if let Some((position, c)) = trace.unknown_char {
+        println!(
+            "  aborted at position {}: {:?} is not in the alphabet",
+            position, c
+        );
+    }
+    println!("{}", if trace.accepted { "ACCEPT" } else { "REJECT" });
+}
+
+/// Helper function to explain why an input was rejected, for the REPL's verbose mode.
+fn print_rejection(dfa: &Dfa, input: &str) {
+    let Some(report) = dfa.explain_rejection(input.chars()) else {
+        return;
+    };
+
+    let key = dfa.state_key(report.state_idx);
+    let state = match &dfa.state_properties[report.state_idx].label {
+        Some(label) => format!("{} ({})", key, label),
+        None => key.to_string(),
+    };
+    match report.reason {
+        // the run only stops in a trap state if it started in one
+        RejectionReason::Trapped if dfa.trap_states[report.state_idx] => {
+            println!("  The start state {} cannot reach an accept state.", state);
+        }
+        RejectionReason::Trapped => {
+            let c = input.chars().nth(report.position).unwrap_or_default();
+            println!(
+                "  At position {}, {:?} leads from state {} into a trap state.",
+                report.position, c, state
+            );
+        }
+        RejectionReason::UnknownChar(c) => {
+            println!(
+                "  At position {}, {:?} is not in the alphabet (in state {}).",
+                report.position, c, state
+            );
+        }
+        RejectionReason::EndedInNonAccepting => {
+            println!("  The input ended in the non-accepting state {}.", state);
+        }
+    }
+
+    if report.viable_symbols.is_empty() {
+        println!("  No symbol would have kept the input viable.");
+    } else {
+        let symbols: Vec<String> = report
+            .viable_symbols
+            .iter()
+            .map(|c| format!("{:?}", c))
+            .collect();
+        println!(
+            "  Symbols that would have kept it viable: {}",
+            symbols.join(" ")
+        );
+    }
+}
+
+/// Helper function to print the analysis report of an FSM file.
+fn run_info(path: &Path, limits: &DeterminizeLimits) -> Result<ExitCode> {
+    let fsm = load_fsm(path, limits)?;
+    let dfa = fsm.dfa();
+    let report = dfa.analyze();
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+
+    println!("FSM: {}", dfa.name);
+    println!(
+        "Kind: {}",
+        match &fsm {
+            Fsm::Dfa(_) => "DFA",
+            Fsm::Nfa { .. } => "NFA (determinized)",
+        }
+    );
+    println!(
+        "Alphabet size: {} ({} classes)",
+        report.alphabet_size, report.class_count
+    );
+    println!(
+        "States: {} ({} reachable, {} co-reachable)",
+        report.state_count, report.reachable_count, report.coreachable_count
+    );
+    println!("Transitions: {}", report.transition_count);
+    println!("Empty: {}", yes_no(report.is_empty));
+    println!("Universal: {}", yes_no(report.is_universal));
+    println!("Finite: {}", yes_no(report.is_finite));
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Helper function to print the number of accepted strings of each length of an FSM file.
+fn run_count(path: &Path, max_len: usize, limits: &DeterminizeLimits) -> Result<ExitCode> {
+    let fsm = load_fsm(path, limits)?;
+    let counts = fsm.dfa().count_accepted_by_length(max_len);
+
+    println!("{:<8}ACCEPTED", "LENGTH");
+    for (len, count) in counts.iter().enumerate() {
+        if *count == u128::MAX {
+            println!("{:<8}>= {} (saturated)", len, count);
+        } else {
+            println!("{:<8}{}", len, count);
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Helper function to print uniformly sampled accepted strings of an FSM file.
+fn run_sample(
+    path: &Path,
+    len: usize,
+    count: usize,
+    seed: Option<u64>,
+    limits: &DeterminizeLimits,
+) -> Result<ExitCode> {
+    let fsm = load_fsm(path, limits)?;
+    let sampler = fsm.dfa().sampler(len)?;
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    };
+
+    for _ in 0..count {
+        println!("{}", sampler.sample(&mut rng));
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Helper function to print a regular expression equivalent to an FSM file.
+fn run_to_regex(path: &Path, use_nfa: bool, limits: &DeterminizeLimits) -> Result<ExitCode> {
+    let fsm = load_fsm(path, limits)?;
+    let regex = match &fsm {
+        Fsm::Nfa { nfa, .. } if use_nfa => nfa.to_regex(),
+        Fsm::Dfa(_) if use_nfa => {
+            return Err(anyhow!("'{}' is not an NFA", path.display()));
+        }
+        _ => fsm.dfa().minimize().to_regex(),
+    };
+
+    println!("{}", regex);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Helper function to print the lines of a text file containing matches of an FSM file.
+fn run_grep(
+    path: &Path,
+    input_path: &Path,
+    first: bool,
+    limits: &DeterminizeLimits,
+) -> Result<ExitCode> {
+    let fsm = load_fsm(path, limits)?;
+    let text = std::fs::read_to_string(input_path)?;
+    let kind = if first {
+        MatchKind::LeftmostFirst
+    } else {
+        MatchKind::LeftmostLongest
+    };
+
+    let mut found = false;
+    for (line_idx, line) in text.lines().enumerate() {
+        let spans: Vec<String> = fsm
+            .dfa()
+            .find_iter(line, kind)
+            .map(|m| format!("{}..{}", m.char_start, m.char_end))
+            .collect();
+        if !spans.is_empty() {
+            found = true;
+            println!("{}:[{}]: {}", line_idx + 1, spans.join(", "), line);
+        }
+    }
+
+    Ok(if found {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+/// Helper function to print the tokens of standard input for an FSM file.
+fn run_lex(path: &Path, limits: &DeterminizeLimits) -> Result<ExitCode> {
+    let fsm = load_fsm(path, limits)?;
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+
+    for token in fsm.dfa().tokenize(&input)? {
+        let token = token?;
+        println!(
+            "{:<12}{:<16}{:?}",
+            format!("{}..{}", token.char_start, token.char_end),
+            token.kind,
+            token.text
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Helper function to print the outputs of a (composed) transducer for an input string.
+fn run_transduce(path: &Path, input: &str, then: &[PathBuf]) -> Result<ExitCode> {
+    let mut transducer = load_transducer(path)?;
+    for next_path in then {
+        transducer = transducer.compose(&load_transducer(next_path)?);
+    }
+
+    let outputs = transducer.outputs(input)?;
+    for output in &outputs {
+        println!("{:?}", output);
+    }
+
+    Ok(if outputs.is_empty() {
+        println!("No output for {:?}", input);
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Helper function to load a transducer from a file path.
+fn load_transducer(path: &Path) -> Result<Transducer> {
+    let contents = std::fs::read_to_string(path)?;
+    fsm::parser::transducer_from_yaml(&contents)
+}
+
+/// Helper function to compare the languages of two FSM files.
+fn run_equiv(
+    first_path: &Path,
+    second_path: &Path,
+    limits: &DeterminizeLimits,
+) -> Result<ExitCode> {
+    let first = load_fsm(first_path, limits)?;
+    let second = load_fsm(second_path, limits)?;
+
+    match fsm::compare::check_equivalence(first.dfa(), second.dfa()) {
+        Equivalence::Equivalent => {
+            println!("EQUIVALENT");
+            Ok(ExitCode::SUCCESS)
+        }
+        Equivalence::Different {
+            witness,
+            accepted_by,
+        } => {
+            let (accepting, rejecting) = match accepted_by {
+                Machine::First => (first_path, second_path),
+                Machine::Second => (second_path, first_path),
+            };
+            println!("NOT EQUIVALENT");
+            println!(
+                "Counterexample: {:?} is accepted by '{}' but rejected by '{}'",
+                witness,
+                accepting.display(),
+                rejecting.display()
+            );
+            Ok(ExitCode::from(1))
+        }
+    }
+}
+
+/// Where the current FSM was loaded from, so that it can be reloaded.
+enum Source {
+    File(PathBuf),
+    Regex(String),
+}
+
+impl Source {
+    /// Returns the path used to name generated visualization files.
+    fn viz_path(&self) -> PathBuf {
+        match self {
+            Source::File(path) => path.clone(),
+            Source::Regex(_) => PathBuf::from("regex.yml"),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Regex(pattern) => write!(f, "/{}/", pattern),
+        }
+    }
+}
+
+/// Helper function to load a FSM from its source, compiling it if it is a regex.
+fn load_source(source: &Source, args: &Args, limits: &DeterminizeLimits) -> Result<Fsm> {
+    match source {
+        Source::File(path) => load_fsm(path, limits),
+        Source::Regex(pattern) => {
+            let extra_alphabet = match &args.alphabet {
+                Some(chars) => match Regex::parse(&format!("[{}]", chars))? {
+                    Regex::Class(set) => set,
+                    _ => return Err(anyhow!("Invalid alphabet: {}", chars)),
+                },
+                None => BTreeSet::new(),
+            };
+            fsm::regex::from_regex_with_limits(pattern, &extra_alphabet, limits)
+        }
+    }
+}
+
 /// Helper function to load a FSM from a file path.
-fn load_fsm(path: &Path) -> Result<Fsm> {
+fn load_fsm(path: &Path, limits: &DeterminizeLimits) -> Result<Fsm> {
     let mut file = File::open(path)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
-    let fsm = fsm::parser::from_yaml(&contents)?;
+    let fsm = fsm::parser::from_yaml_with_limits(&contents, limits)?;
 
     Ok(fsm)
 }
 
+/// Helper function to check that the language of one FSM file is contained in another's.
+fn run_subset(
+    subset_path: &Path,
+    superset_path: &Path,
+    limits: &DeterminizeLimits,
+) -> Result<ExitCode> {
+    let subset = load_fsm(subset_path, limits)?;
+    let superset = load_fsm(superset_path, limits)?;
+
+    match fsm::compare::check_inclusion(subset.dfa(), superset.dfa()) {
+        Inclusion::Included => {
+            println!("INCLUDED");
+            Ok(ExitCode::SUCCESS)
+        }
+        Inclusion::NotIncluded { witness } => {
+            println!("NOT INCLUDED");
+            println!(
+                "Witness: {:?} is accepted by '{}' but rejected by '{}'",
+                witness,
+                subset_path.display(),
+                superset_path.display()
+            );
+            Ok(ExitCode::from(1))
+        }
+    }
+}
+
+/// Helper function to apply the transformations requested on the command line.
+fn prepare_fsm(fsm: Fsm, args: &Args) -> Fsm {
+    // complementing an NFA directly is incorrect, so the result is always a plain DFA
+    let fsm = if args.complement {
+        Fsm::Dfa(fsm.dfa().complement())
+    } else {
+        fsm
+    };
+
+    if !args.minimize {
+        return fsm;
+    }
+
+    match fsm {
+        Fsm::Dfa(dfa) => Fsm::Dfa(dfa.minimize()),
+        Fsm::Nfa { nfa, dfa } => Fsm::Nfa {
+            nfa,
+            dfa: dfa.minimize(),
+        },
+    }
+}
+
 /// Helper function to run the visualization logic.
 fn run_viz(fsm: &Fsm, file_path: &Path) -> Result<()> {
     match fsm {
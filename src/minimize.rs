@@ -0,0 +1,222 @@
+use bimap::BiMap;
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::dfa::{Dfa, StateInfo};
+
+impl Dfa {
+    /// Returns an equivalent DFA with the minimum number of states.
+    ///
+    /// Unreachable states are dropped and indistinguishable states are merged using Hopcroft's
+    /// partition refinement. Each merged state keeps the key of the member first reached from the
+    /// start state, and its label lists the keys of all merged members. States recognizing
+    /// different tokens or outputs are never merged.
+    pub fn minimize(&self) -> Dfa {
+        // partition refinement works on symbol classes: symbols of a class act identically
+        let class_count = self.class_count;
+
+        // discover reachable states in breadth-first order, which also fixes the output ordering
+        let reachable = self.reachable_in_bfs_order();
+        let mut local_idx = vec![usize::MAX; self.state_keys.len()];
+        for (i, &state) in reachable.iter().enumerate() {
+            local_idx[state] = i;
+        }
+        let n = reachable.len();
+
+        // inverse transitions over reachable states: (class_idx, dest) -> sources
+        let mut inverse: Vec<Vec<usize>> = vec![Vec::new(); class_count * n];
+        for (src, &state) in reachable.iter().enumerate() {
+            for class_idx in 0..class_count {
+                let dest = local_idx[self.class_transition(state, class_idx)];
+                inverse[class_idx * n + dest].push(src);
+            }
+        }
+
+        // initial partition: by acceptance, recognized token and the Moore output and Mealy
+        // outputs of the state, so that states behaving differently are never merged
+        // (accept, token, Moore output, Mealy output per symbol class) -> block
+        type Class<'a> = (bool, Option<&'a str>, Option<&'a str>, Vec<Option<&'a str>>);
+        let mut blocks: Vec<Vec<usize>> = Vec::new();
+        let mut initial: BTreeMap<Class, usize> = BTreeMap::new();
+        let mut block_of = vec![0; n];
+        for (i, &state) in reachable.iter().enumerate() {
+            let class = (
+                self.accept_states[state],
+                self.state_properties[state].token.as_deref(),
+                self.state_properties[state].output.as_deref(),
+                (0..class_count)
+                    .map(|class_idx| self.class_transition_output(state, class_idx))
+                    .collect(),
+            );
+            let block = *initial.entry(class).or_insert_with(|| {
+                blocks.push(Vec::new());
+                blocks.len() - 1
+            });
+            blocks[block].push(i);
+            block_of[i] = block;
+        }
+
+        // every block but the largest is a splitter for every symbol class.
+        // (block * class_count) + class_idx -> whether the pair is pending in the worklist
+        let mut worklist: VecDeque<(usize, usize)> = VecDeque::new();
+        let mut in_worklist = vec![false; blocks.len() * class_count];
+        let largest = (0..blocks.len()).max_by_key(|&b| blocks[b].len());
+        for block in (0..blocks.len()).filter(|&b| Some(b) != largest) {
+            for class_idx in 0..class_count {
+                worklist.push_back((block, class_idx));
+                in_worklist[(block * class_count) + class_idx] = true;
+            }
+        }
+
+        let mut marked = vec![false; n];
+        while let Some((splitter, class_idx)) = worklist.pop_front() {
+            in_worklist[(splitter * class_count) + class_idx] = false;
+
+            // states that move into the splitter block on this class, grouped by their block
+            let mut touched: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+            for &dest in &blocks[splitter] {
+                for &src in &inverse[class_idx * n + dest] {
+                    touched.entry(block_of[src]).or_default().push(src);
+                }
+            }
+
+            for (block, members) in touched {
+                if members.len() == blocks[block].len() {
+                    continue;
+                }
+
+                for &m in &members {
+                    marked[m] = true;
+                }
+                let (moved, kept): (Vec<usize>, Vec<usize>) =
+                    blocks[block].iter().partition(|&&s| marked[s]);
+                for &m in &members {
+                    marked[m] = false;
+                }
+
+                let new_block = blocks.len();
+                for &s in &moved {
+                    block_of[s] = new_block;
+                }
+                let moved_len = moved.len();
+                let kept_len = kept.len();
+                blocks[block] = kept;
+                blocks.push(moved);
+                in_worklist.extend(std::iter::repeat_n(false, class_count));
+
+                // if the old block was pending, both halves must be; otherwise the smaller suffices
+                for a in 0..class_count {
+                    let add = if in_worklist[(block * class_count) + a] || moved_len <= kept_len {
+                        new_block
+                    } else {
+                        block
+                    };
+                    if !in_worklist[(add * class_count) + a] {
+                        in_worklist[(add * class_count) + a] = true;
+                        worklist.push_back((add, a));
+                    }
+                }
+            }
+        }
+
+        // number the resulting blocks in breadth-first order from the start block
+        let mut new_idx = vec![usize::MAX; blocks.len()];
+        let mut order = Vec::new();
+        let mut queue = VecDeque::from([block_of[0]]);
+        new_idx[block_of[0]] = 0;
+        while let Some(block) = queue.pop_front() {
+            order.push(block);
+            let representative = reachable[blocks[block][0]];
+            for class_idx in 0..class_count {
+                let dest_block =
+                    block_of[local_idx[self.class_transition(representative, class_idx)]];
+                if new_idx[dest_block] == usize::MAX {
+                    new_idx[dest_block] = order.len() + queue.len();
+                    queue.push_back(dest_block);
+                }
+            }
+        }
+
+        let mut state_keys = BiMap::new();
+        let mut state_properties = Vec::with_capacity(order.len());
+        let mut transition_table = Vec::with_capacity(order.len() * self.alphabet.len());
+        let mut transition_outputs = Vec::new();
+
+        for (idx, &block) in order.iter().enumerate() {
+            let mut members = blocks[block].clone();
+            members.sort();
+            let representative = reachable[members[0]];
+            let key = self
+                .state_keys
+                .get_by_right(&representative)
+                .cloned()
+                .unwrap_or_else(|| {
+                    panic!("State index {} not found in state_keys", representative)
+                });
+
+            let label = if members.len() == 1 {
+                self.state_properties[representative].label.clone()
+            } else {
+                let member_keys: Vec<&str> = members
+                    .iter()
+                    .filter_map(|&m| self.state_keys.get_by_right(&reachable[m]))
+                    .map(String::as_str)
+                    .collect();
+                Some(format!("{{{}}}", member_keys.join(",")))
+            };
+
+            let accept = self.accept_states[representative];
+            let token = self.state_properties[representative].token.clone();
+            let output = self.state_properties[representative].output.clone();
+            state_keys.insert(key, idx);
+            state_properties.push(StateInfo {
+                label,
+                accept,
+                token,
+                output,
+            });
+
+            for alpha_idx in 0..self.alphabet.len() {
+                let dest_block = block_of[local_idx[self.transition(representative, alpha_idx)]];
+                transition_table.push(new_idx[dest_block]);
+                if !self.class_outputs.is_empty() {
+                    transition_outputs.push(
+                        self.transition_output(representative, alpha_idx)
+                            .map(str::to_string),
+                    );
+                }
+            }
+        }
+
+        Dfa::new(
+            self.name.clone(),
+            self.description.clone(),
+            self.alphabet.clone(),
+            state_keys,
+            0,
+            transition_table,
+            state_properties,
+        )
+        .with_transition_outputs(transition_outputs)
+    }
+
+    /// Returns the states reachable from the start state, in breadth-first order.
+    pub(crate) fn reachable_in_bfs_order(&self) -> Vec<usize> {
+        let mut visited = vec![false; self.state_keys.len()];
+        let mut order = Vec::new();
+        let mut queue = VecDeque::from([self.start_state_idx]);
+        visited[self.start_state_idx] = true;
+
+        while let Some(state) = queue.pop_front() {
+            order.push(state);
+            for class_idx in 0..self.class_count {
+                let dest = self.class_transition(state, class_idx);
+                if !visited[dest] {
+                    visited[dest] = true;
+                    queue.push_back(dest);
+                }
+            }
+        }
+
+        order
+    }
+}
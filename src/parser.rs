@@ -1,4 +1,8 @@
+use crate::classes::{self, ClassRefiner, CompressedTable};
 use crate::dfa::{Dfa, StateInfo};
+use crate::lazy::LazyDfa;
+use crate::symbolic::{IntervalSet, SymbolicDfa, SymbolicNfa};
+use crate::transducer::{Transducer, TransducerTransitions};
 use anyhow::{Result, anyhow};
 use bimap::BiMap;
 use serde::{
@@ -21,6 +25,17 @@ pub struct Nfa {
     pub nfa_accept_states: BTreeSet<usize>,
     /// Original state keys, used for creating labels for new DFA states.
     pub nfa_state_keys: BiMap<String, usize>,
+    /// Tokens recognized by accepting NFA states, used to tag the DFA states containing them.
+    pub nfa_tokens: BTreeMap<usize, TokenTag>,
+}
+
+/// A token name attached to an accept state, with the priority used to resolve conflicts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenTag {
+    pub name: String,
+    /// When a DFA state contains accepting NFA states with different tokens, the token with the
+    /// highest priority wins.
+    pub priority: i64,
 }
 
 pub enum Fsm {
@@ -28,12 +43,137 @@ pub enum Fsm {
     Nfa { nfa: Nfa, dfa: Dfa },
 }
 
+/// Bounds on the size of the DFA built by subset construction. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeterminizeLimits {
+    /// The maximum number of DFA states, including the dead state.
+    pub max_states: Option<usize>,
+    /// The maximum number of transition table cells (DFA states times alphabet size).
+    pub max_cells: Option<usize>,
+}
+
+/// Which of the [`DeterminizeLimits`] was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceededLimit {
+    States(usize),
+    Cells(usize),
+}
+
+/// The error returned when subset construction exceeds its [`DeterminizeLimits`].
+///
+/// It is wrapped in an [`anyhow::Error`] and can be recovered with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: ExceededLimit,
+    /// The number of DFA states discovered when construction stopped.
+    pub discovered_states: usize,
+    /// The NFA state sets (as sorted NFA state keys) still waiting in the worklist.
+    pub worklist: Vec<Vec<String>>,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const SHOWN_SETS: usize = 5;
+
+        match self.limit {
+            ExceededLimit::States(max) => write!(
+                f,
+                "Subset construction exceeded the limit of {} DFA states",
+                max
+            )?,
+            ExceededLimit::Cells(max) => write!(
+                f,
+                "Subset construction exceeded the limit of {} transition table cells",
+                max
+            )?,
+        }
+        write!(
+            f,
+            " ({} states discovered, {} NFA state sets in the worklist",
+            self.discovered_states,
+            self.worklist.len()
+        )?;
+
+        let shown: Vec<String> = self
+            .worklist
+            .iter()
+            .take(SHOWN_SETS)
+            .map(|set| format!("{{{}}}", set.join(",")))
+            .collect();
+        if !shown.is_empty() {
+            write!(f, ": {}", shown.join(", "))?;
+            if self.worklist.len() > SHOWN_SETS {
+                write!(f, ", ...")?;
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl LimitExceeded {
+    /// Builds the error describing where subset construction stopped, naming the NFA states of
+    /// the sets left in the worklist by their keys.
+    pub(crate) fn new(
+        limit: ExceededLimit,
+        discovered_states: usize,
+        worklist: &VecDeque<BTreeSet<usize>>,
+        state_keys: &BiMap<String, usize>,
+    ) -> LimitExceeded {
+        let worklist = worklist
+            .iter()
+            .map(|set| {
+                let mut keys: Vec<String> = set
+                    .iter()
+                    .filter_map(|id| state_keys.get_by_right(id).cloned())
+                    .collect();
+                keys.sort();
+                keys
+            })
+            .collect();
+
+        LimitExceeded {
+            limit,
+            discovered_states,
+            worklist,
+        }
+    }
+}
+
+impl DeterminizeLimits {
+    /// Returns the limit exceeded by a DFA with `states` states over `alphabet_size` symbols.
+    fn check(&self, states: usize, alphabet_size: usize) -> Option<ExceededLimit> {
+        self.exceeded(states, states.saturating_mul(alphabet_size))
+    }
+
+    /// Returns the limit exceeded by a DFA with `states` states and `cells` transitions.
+    pub(crate) fn exceeded(&self, states: usize, cells: usize) -> Option<ExceededLimit> {
+        match (self.max_states, self.max_cells) {
+            (Some(max), _) if states > max => Some(ExceededLimit::States(max)),
+            (_, Some(max)) if cells > max => Some(ExceededLimit::Cells(max)),
+            _ => None,
+        }
+    }
+}
+
+impl Fsm {
+    /// Returns the deterministic automaton, which for an NFA is the result of subset construction.
+    pub fn dfa(&self) -> &Dfa {
+        match self {
+            Fsm::Dfa(dfa) => dfa,
+            Fsm::Nfa { dfa, .. } => dfa,
+        }
+    }
+}
+
 impl Nfa {
     /// Creates an NFA from the parsed YAML components.
     fn from_yaml(
         state_bimap: &BiMap<String, usize>,
         start_state_idx: usize,
         state_infos: &[StateInfo],
+        nfa_tokens: BTreeMap<usize, TokenTag>,
         yaml_transitions: BTreeMap<String, Vec<YamlTransitionMapping>>,
         full_alphabet_set: &BTreeSet<char>,
     ) -> Result<Self> {
@@ -75,15 +215,19 @@ impl Nfa {
             start_state: start_state_idx,
             nfa_accept_states,
             nfa_state_keys: state_bimap.clone(),
+            nfa_tokens,
         })
     }
 
     /// Converts the NFA to an equivalent DFA using subset construction.
-    fn to_dfa(
-        self,
+    ///
+    /// Fails with a [`LimitExceeded`] error as soon as the DFA outgrows `limits`.
+    pub(crate) fn to_dfa(
+        &self,
         name: &str,
         description: Option<String>,
         alphabet_set: &BTreeSet<char>,
+        limits: &DeterminizeLimits,
     ) -> Result<Dfa> {
         let alphabet: Vec<char> = alphabet_set.iter().cloned().collect();
         let alphabet_bimap: BiMap<char, usize> = alphabet
@@ -93,13 +237,31 @@ impl Nfa {
             .map(|(i, c)| (c, i))
             .collect();
 
+        // symbols with the same transitions from every NFA state share a class, so subset
+        // construction only needs to follow one representative of each class
+        let mut refiner = ClassRefiner::new(alphabet.len());
+        for src in 0..self.nfa_state_keys.len() {
+            let on_chars = self
+                .transitions
+                .range((src, Some('\0'))..=(src, Some(char::MAX)));
+            refiner.refine(on_chars.filter_map(|(&(_, symbol), dests)| {
+                let alpha_idx = *alphabet_bimap.get_by_left(&symbol?)?;
+                Some((alpha_idx, dests))
+            }));
+        }
+        let (char_classes, class_count) = refiner.finish();
+        let mut representatives = vec![None; class_count];
+        for (&symbol, &class_idx) in alphabet.iter().zip(&char_classes) {
+            representatives[class_idx].get_or_insert(symbol);
+        }
+        let representatives: Vec<char> = representatives.into_iter().flatten().collect();
+
         // set of NFA states to new DFA state index
         let mut dfa_states: BTreeMap<BTreeSet<usize>, usize> = BTreeMap::new();
         let mut worklist: VecDeque<BTreeSet<usize>> = VecDeque::new();
 
         let mut dfa_state_keys = BiMap::new();
         let mut dfa_state_properties = Vec::new();
-        let mut dfa_accept_states = Vec::new();
         let mut dfa_transitions = BTreeMap::new();
 
         let start_nfa_set = self.epsilon_closure(&BTreeSet::from([self.start_state]));
@@ -111,7 +273,7 @@ impl Nfa {
         while let Some(current_nfa_set) = worklist.pop_front() {
             let current_dfa_idx = *dfa_states.get(&current_nfa_set).unwrap();
 
-            for (alpha_idx, &symbol) in alphabet.iter().enumerate() {
+            for (class_idx, &symbol) in representatives.iter().enumerate() {
                 let directly_reachable_states = self.move_on_char(&current_nfa_set, symbol);
                 let target_nfa_set = self.epsilon_closure(&directly_reachable_states);
 
@@ -124,12 +286,21 @@ impl Nfa {
                     idx
                 } else {
                     let new_idx = dfa_states.len();
+                    if let Some(limit) = limits.check(new_idx + 1, alphabet.len()) {
+                        return Err(LimitExceeded::new(
+                            limit,
+                            new_idx,
+                            &worklist,
+                            &self.nfa_state_keys,
+                        )
+                        .into());
+                    }
                     dfa_states.insert(target_nfa_set.clone(), new_idx);
                     worklist.push_back(target_nfa_set);
                     new_idx
                 };
 
-                dfa_transitions.insert((current_dfa_idx, alpha_idx), next_dfa_idx);
+                dfa_transitions.insert((current_dfa_idx, class_idx), next_dfa_idx);
             }
         }
 
@@ -137,7 +308,7 @@ impl Nfa {
         let num_dfa_states = dfa_states.len();
         let mut needs_dead_state = false;
         'outer: for i in 0..num_dfa_states {
-            for j in 0..alphabet.len() {
+            for j in 0..class_count {
                 if !dfa_transitions.contains_key(&(i, j)) {
                     needs_dead_state = true;
                     break 'outer;
@@ -147,7 +318,10 @@ impl Nfa {
 
         let dead_state_idx = if needs_dead_state {
             let idx = num_dfa_states;
-            for j in 0..alphabet.len() {
+            if let Some(limit) = limits.check(idx + 1, alphabet.len()) {
+                return Err(LimitExceeded::new(limit, idx, &worklist, &self.nfa_state_keys).into());
+            }
+            for j in 0..class_count {
                 dfa_transitions.insert((idx, j), idx);
             }
             Some(idx)
@@ -165,7 +339,6 @@ impl Nfa {
                 .intersection(&self.nfa_accept_states)
                 .next()
                 .is_some();
-            dfa_accept_states.push(is_accepting);
 
             let mut state_keys: Vec<&str> = nfa_set
                 .iter()
@@ -174,10 +347,13 @@ impl Nfa {
             state_keys.sort();
 
             let new_key = format!("{{{}}}", state_keys.join(","));
+            let token = self.resolve_token(&nfa_set, &new_key)?;
             dfa_state_keys.insert(new_key.clone(), dfa_idx);
             dfa_state_properties.push(StateInfo {
                 label: Some(new_key),
                 accept: is_accepting,
+                token,
+                output: None,
             });
         }
 
@@ -187,46 +363,65 @@ impl Nfa {
             dfa_state_properties.push(StateInfo {
                 label: Some(key),
                 accept: false,
+                token: None,
+                output: None,
             });
-            dfa_accept_states.push(false);
         }
 
-        let mut transition_table =
-            vec![dead_state_idx.unwrap_or(0); total_dfa_states * alphabet.len()];
-        for ((from, alpha), to) in dfa_transitions {
-            transition_table[from * alphabet.len() + alpha] = to;
+        let mut class_table = vec![dead_state_idx.unwrap_or(0); total_dfa_states * class_count];
+        for ((from, class_idx), to) in dfa_transitions {
+            class_table[from * class_count + class_idx] = to;
         }
 
-        Ok(Dfa {
-            name: name.to_string(),
+        Ok(Dfa::from_classes(
+            name.to_string(),
             description,
-            alphabet: alphabet_bimap,
-            state_keys: dfa_state_keys,
-            start_state_idx: start_dfa_idx,
-            accept_states: dfa_accept_states,
-            transition_table,
-            state_properties: dfa_state_properties,
-        })
+            alphabet_bimap,
+            dfa_state_keys,
+            start_dfa_idx,
+            classes::merge(&char_classes, class_count, &class_table, &[]),
+            dfa_state_properties,
+        ))
     }
 
-    /// Calculates the epsilon closure for a given set of NFA states.
-    fn epsilon_closure(&self, states: &BTreeSet<usize>) -> BTreeSet<usize> {
-        let mut closure = states.clone();
-        let mut worklist: Vec<usize> = states.iter().cloned().collect();
-        while let Some(state) = worklist.pop() {
-            if let Some(epsilon_dests) = self.transitions.get(&(state, None)) {
-                for &dest in epsilon_dests {
-                    if closure.insert(dest) {
-                        worklist.push(dest);
-                    }
+    /// Picks the token recognized by a DFA state from the tokens of its NFA states.
+    ///
+    /// The token with the highest priority wins; two different tokens sharing the highest
+    /// priority are an error, since the lexer could not tell which one was meant.
+    fn resolve_token(&self, nfa_set: &BTreeSet<usize>, dfa_key: &str) -> Result<Option<String>> {
+        let mut best: Option<&TokenTag> = None;
+        for tag in nfa_set.iter().filter_map(|s| self.nfa_tokens.get(s)) {
+            match best {
+                Some(current) if current.priority > tag.priority => {}
+                Some(current) if current.priority == tag.priority && current.name != tag.name => {
+                    return Err(anyhow!(
+                        "Conflicting tokens '{}' and '{}' with priority {} in DFA state '{}'; \
+                         give one of them a higher priority",
+                        current.name,
+                        tag.name,
+                        tag.priority,
+                        dfa_key
+                    ));
                 }
+                _ => best = Some(tag),
             }
         }
-        closure
+        Ok(best.map(|tag| tag.name.clone()))
+    }
+
+    /// Calculates the epsilon closure for a given set of NFA states.
+    pub(crate) fn epsilon_closure(&self, states: &BTreeSet<usize>) -> BTreeSet<usize> {
+        epsilon_closure(states.clone(), |state| {
+            self.transitions
+                .get(&(state, None))
+                .into_iter()
+                .flatten()
+                .cloned()
+        })
     }
 
     /// Finds all states reachable from a set of states on a given character.
-    fn move_on_char(&self, states: &BTreeSet<usize>, symbol: char) -> BTreeSet<usize> {
+    pub(crate) fn move_on_char(&self, states: &BTreeSet<usize>, symbol: char) -> BTreeSet<usize> {
         let mut result = BTreeSet::new();
         for &state in states {
             if let Some(dests) = self.transitions.get(&(state, Some(symbol))) {
@@ -237,6 +432,28 @@ impl Nfa {
     }
 }
 
+/// Returns `states` together with every state reachable from them through epsilon transitions,
+/// where `epsilon_successors` lists the destinations of the epsilon transitions leaving a state.
+pub(crate) fn epsilon_closure<F, I>(
+    states: BTreeSet<usize>,
+    epsilon_successors: F,
+) -> BTreeSet<usize>
+where
+    F: Fn(usize) -> I,
+    I: IntoIterator<Item = usize>,
+{
+    let mut worklist: Vec<usize> = states.iter().cloned().collect();
+    let mut closure = states;
+    while let Some(state) = worklist.pop() {
+        for dest in epsilon_successors(state) {
+            if closure.insert(dest) {
+                worklist.push(dest);
+            }
+        }
+    }
+    closure
+}
+
 #[derive(Deserialize, Debug)]
 struct YamlDFA {
     name: String,
@@ -254,6 +471,9 @@ struct YamlStateProps {
     #[serde(default)]
     accept: bool,
     label: Option<String>,
+    token: Option<String>,
+    priority: Option<i64>,
+    output: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -305,11 +525,24 @@ impl<'de> Deserialize<'de> for YamlSymbolSpecifier {
 impl YamlSymbolSpecifier {
     fn to_char_set(&self) -> Result<BTreeSet<char>> {
         let mut char_set = BTreeSet::new();
+        for (start, end) in self.to_ranges()? {
+            // surrogate code points are not characters and are skipped
+            char_set.extend(((start as u32)..=(end as u32)).filter_map(char::from_u32));
+        }
+        Ok(char_set)
+    }
+
+    /// Returns the symbols as an [`IntervalSet`], without listing every character of a range.
+    fn to_interval_set(&self) -> Result<IntervalSet> {
+        Ok(IntervalSet::from_ranges(self.to_ranges()?))
+    }
+
+    /// Returns the inclusive character ranges making up the specified symbols.
+    fn to_ranges(&self) -> Result<Vec<(char, char)>> {
+        let mut ranges = Vec::new();
         match self {
             YamlSymbolSpecifier::Literal(s) => {
-                for c in s.chars() {
-                    char_set.insert(c);
-                }
+                ranges.extend(s.chars().map(|c| (c, c)));
             }
             YamlSymbolSpecifier::Map(range_map) => {
                 if let Some(crange) = &range_map.crange {
@@ -317,25 +550,17 @@ impl YamlSymbolSpecifier {
                     if parts.len() != 2 {
                         return Err(anyhow!("Invalid character range: {}", crange));
                     }
-                    let start = parts[0]
-                        .chars()
-                        .next()
-                        .ok_or_else(|| anyhow!("Empty start in range: {}", crange))?;
-                    let end = parts[1]
-                        .chars()
-                        .next()
-                        .ok_or_else(|| anyhow!("Empty end in range: {}", crange))?;
+                    let start = parse_range_char(parts[0])
+                        .ok_or_else(|| anyhow!("Invalid start in range: {}", crange))?;
+                    let end = parse_range_char(parts[1])
+                        .ok_or_else(|| anyhow!("Invalid end in range: {}", crange))?;
                     if start > end {
                         return Err(anyhow!(
                             "Start character greater than end in range: {}",
                             crange
                         ));
                     }
-                    for c in (start as u32)..=(end as u32) {
-                        char_set.insert(std::char::from_u32(c).unwrap_or_else(|| {
-                            panic!("Invalid character in range: {}..{}", start, end)
-                        }));
-                    }
+                    ranges.push((start, end));
                 }
                 if let Some(nrange) = &range_map.nrange {
                     let parts: Vec<&str> = nrange.split("..").collect();
@@ -357,13 +582,22 @@ impl YamlSymbolSpecifier {
                     if start > 9 || end > 9 {
                         return Err(anyhow!("Numeric range must be between 0 and 9: {}", nrange));
                     }
-                    for n in start..=end {
-                        char_set.insert((b'0' + n) as char);
-                    }
+                    ranges.push(((b'0' + start) as char, (b'0' + end) as char));
                 }
             }
         }
-        Ok(char_set)
+        Ok(ranges)
+    }
+}
+
+/// Parses an endpoint of a `crange`: a single character, or a code point escape like `\u{10FFFF}`.
+fn parse_range_char(s: &str) -> Option<char> {
+    match s
+        .strip_prefix("\\u{")
+        .and_then(|rest| rest.strip_suffix('}'))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+        None => s.chars().next(),
     }
 }
 
@@ -397,6 +631,11 @@ enum TransitionTrigger {
     Epsilon,
 }
 
+enum IntervalTrigger {
+    Symbols(IntervalSet),
+    Epsilon,
+}
+
 impl YamlTransitionOn {
     fn to_transition_trigger(&self, full_alphabet: &BTreeSet<char>) -> Result<TransitionTrigger> {
         match self {
@@ -431,6 +670,37 @@ impl YamlTransitionOn {
     }
 }
 
+impl YamlTransitionOn {
+    /// Like [`YamlTransitionOn::to_transition_trigger`], but keeps the symbols as intervals.
+    fn to_interval_trigger(&self, full_alphabet: &IntervalSet) -> Result<IntervalTrigger> {
+        let union = |specs: &[YamlSymbolSpecifier]| -> Result<IntervalSet> {
+            let mut set = IntervalSet::new();
+            for spec in specs {
+                set = set.union(&spec.to_interval_set()?);
+            }
+            Ok(set)
+        };
+
+        match self {
+            YamlTransitionOn::Single(spec) => Ok(IntervalTrigger::Symbols(spec.to_interval_set()?)),
+            YamlTransitionOn::Multiple(specs) => Ok(IntervalTrigger::Symbols(union(specs)?)),
+            YamlTransitionOn::Keyword(kw) => match kw {
+                YamlKeyword::Alphabet => Ok(IntervalTrigger::Symbols(full_alphabet.clone())),
+                YamlKeyword::Epsilon => Ok(IntervalTrigger::Epsilon),
+            },
+            YamlTransitionOn::Except { except } => {
+                let except_set = match except {
+                    YamlExceptValue::Single(spec) => spec.to_interval_set()?,
+                    YamlExceptValue::Multiple(specs) => union(specs)?,
+                };
+                Ok(IntervalTrigger::Symbols(
+                    full_alphabet.difference(&except_set),
+                ))
+            }
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(untagged)]
 enum YamlExceptValue {
@@ -442,11 +712,76 @@ enum YamlExceptValue {
 struct YamlTransitionMapping {
     to: String,
     on: YamlTransitionOn,
+    output: Option<String>,
+    out: Option<String>,
+}
+
+/// A parsed and validated FSM-YAML spec, before any subset construction.
+// only ever built once per load and immediately unpacked, so its size does not matter
+#[allow(clippy::large_enum_variant)]
+enum ParsedSpec {
+    Dfa(Dfa),
+    Nfa {
+        nfa: Nfa,
+        name: String,
+        description: Option<String>,
+        alphabet: BTreeSet<char>,
+    },
 }
 
 pub fn from_yaml(yaml_content: &str) -> Result<Fsm> {
+    from_yaml_with_limits(yaml_content, &DeterminizeLimits::default())
+}
+
+/// Like [`from_yaml`], but fails with a [`LimitExceeded`] error if subset construction for an
+/// NFA spec outgrows `limits`.
+pub fn from_yaml_with_limits(yaml_content: &str, limits: &DeterminizeLimits) -> Result<Fsm> {
+    match parse_spec(yaml_content)? {
+        ParsedSpec::Dfa(dfa) => Ok(Fsm::Dfa(dfa)),
+        ParsedSpec::Nfa {
+            nfa,
+            name,
+            description,
+            alphabet,
+        } => {
+            let dfa = nfa.to_dfa(&name, description, &alphabet, limits)?;
+            Ok(Fsm::Nfa { nfa, dfa })
+        }
+    }
+}
+
+/// Parses an NFA spec for lazy determinization, without running subset construction up front.
+/// At most `max_cached_states` DFA states are kept in the cache at any time.
+pub fn lazy_from_yaml(yaml_content: &str, max_cached_states: usize) -> Result<LazyDfa> {
+    match parse_spec(yaml_content)? {
+        ParsedSpec::Dfa(dfa) => Err(anyhow!(
+            "'{}' is already a DFA; lazy determinization only applies to NFAs",
+            dfa.name
+        )),
+        ParsedSpec::Nfa {
+            nfa,
+            name,
+            description,
+            ..
+        } => Ok(LazyDfa::new(name, description, nfa, max_cached_states)),
+    }
+}
+
+fn parse_spec(yaml_content: &str) -> Result<ParsedSpec> {
     let yaml_dfa: YamlDFA = serde_yaml::from_str(yaml_content)?;
 
+    if let Some(src_key) = yaml_dfa
+        .transitions
+        .iter()
+        .find(|(_, mappings)| mappings.iter().any(|mapping| mapping.out.is_some()))
+        .map(|(key, _)| key)
+    {
+        return Err(anyhow!(
+            "Transition 'out' is only allowed in transducer specs (state '{}')",
+            src_key
+        ));
+    }
+
     let alphabet_set = read_alphabet(&yaml_dfa.alphabet)?;
     let alphabet_bimap: BiMap<char, usize> = alphabet_set
         .iter()
@@ -463,47 +798,254 @@ pub fn from_yaml(yaml_content: &str) -> Result<Fsm> {
         .map(|(i, k)| (k, i))
         .collect();
 
+    let mut nfa_tokens = BTreeMap::new();
+    for (idx, (key, props)) in yaml_dfa.states.iter().enumerate() {
+        match &props.token {
+            Some(_) if !props.accept => {
+                return Err(anyhow!(
+                    "State '{}' has a token but is not an accept state",
+                    key
+                ));
+            }
+            Some(name) => {
+                nfa_tokens.insert(
+                    idx,
+                    TokenTag {
+                        name: name.clone(),
+                        priority: props.priority.unwrap_or(0),
+                    },
+                );
+            }
+            None if props.priority.is_some() => {
+                return Err(anyhow!("State '{}' has a priority but no token", key));
+            }
+            None => {}
+        }
+    }
+
     let state_props: Vec<YamlStateProps> = yaml_dfa.states.values().cloned().collect();
     let state_infos: Vec<StateInfo> = state_props
         .into_iter()
         .map(|p| StateInfo {
             label: p.label,
             accept: p.accept,
+            token: p.token,
+            output: p.output,
         })
         .collect();
 
     let start_state_index = get_state_idx(&state_bimap, &yaml_dfa.start_state)?;
 
     if yaml_dfa.dfa {
-        let transition_table = build_dfa_transitions(
+        let table = build_dfa_transitions(
             &state_bimap,
             yaml_dfa.transitions,
             &alphabet_set,
             &alphabet_bimap,
         )?;
-        let accept_states = state_infos.iter().map(|info| info.accept).collect();
-        Ok(Fsm::Dfa(Dfa {
-            name: yaml_dfa.name,
-            description: yaml_dfa.description,
-            alphabet: alphabet_bimap,
-            state_keys: state_bimap,
-            start_state_idx: start_state_index,
-            accept_states,
-            transition_table,
-            state_properties: state_infos,
-        }))
+        Ok(ParsedSpec::Dfa(Dfa::from_classes(
+            yaml_dfa.name,
+            yaml_dfa.description,
+            alphabet_bimap,
+            state_bimap,
+            start_state_index,
+            table,
+            state_infos,
+        )))
     } else {
+        // outputs of nondeterministic machines are handled by `transducer`, not subset construction
+        let has_outputs = state_infos.iter().any(|info| info.output.is_some())
+            || yaml_dfa
+                .transitions
+                .values()
+                .flatten()
+                .any(|mapping| mapping.output.is_some());
+        if has_outputs {
+            return Err(anyhow!(
+                "State and transition outputs are only allowed when 'dfa' flag is true"
+            ));
+        }
+
         let nfa = Nfa::from_yaml(
             &state_bimap,
             start_state_index,
             &state_infos,
+            nfa_tokens,
             yaml_dfa.transitions,
             &alphabet_set,
         )?;
-        let dfa = nfa
-            .clone()
-            .to_dfa(&yaml_dfa.name, yaml_dfa.description, &alphabet_set)?;
-        Ok(Fsm::Nfa { nfa, dfa })
+        Ok(ParsedSpec::Nfa {
+            nfa,
+            name: yaml_dfa.name,
+            description: yaml_dfa.description,
+            alphabet: alphabet_set,
+        })
+    }
+}
+
+/// Parses a nondeterministic transducer, whose transitions write the string given by `out` (or
+/// copy the character they read if `out` is omitted).
+pub fn transducer_from_yaml(yaml_content: &str) -> Result<Transducer> {
+    let yaml_dfa: YamlDFA = serde_yaml::from_str(yaml_content)?;
+
+    if yaml_dfa.dfa {
+        return Err(anyhow!("Transducers cannot set the 'dfa' flag"));
+    }
+
+    let alphabet_set = read_alphabet(&yaml_dfa.alphabet)?;
+    let state_bimap: BiMap<String, usize> = yaml_dfa
+        .states
+        .keys()
+        .cloned()
+        .enumerate()
+        .map(|(i, k)| (k, i))
+        .collect();
+    let start_state = get_state_idx(&state_bimap, &yaml_dfa.start_state)?;
+
+    let mut accept_states = BTreeSet::new();
+    for (idx, (key, props)) in yaml_dfa.states.iter().enumerate() {
+        if props.output.is_some() || props.token.is_some() {
+            return Err(anyhow!(
+                "State '{}': transducer states cannot have an output or a token",
+                key
+            ));
+        }
+        if props.accept {
+            accept_states.insert(idx);
+        }
+    }
+
+    let mut transitions = TransducerTransitions::new();
+    for (src_key, mappings) in yaml_dfa.transitions {
+        let src_idx = get_state_idx(&state_bimap, &src_key)?;
+        for mapping in mappings {
+            if mapping.output.is_some() {
+                return Err(anyhow!(
+                    "Transducer transitions write 'out', not 'output' (state '{}')",
+                    src_key
+                ));
+            }
+            let dest_idx = get_state_idx(&state_bimap, &mapping.to)?;
+            let triggers: Vec<Option<char>> =
+                match mapping.on.to_transition_trigger(&alphabet_set)? {
+                    TransitionTrigger::Epsilon => vec![None],
+                    TransitionTrigger::Chars(chars) => chars.into_iter().map(Some).collect(),
+                };
+            for on in triggers {
+                // without an explicit `out`, a transition copies the character it reads
+                let out = match &mapping.out {
+                    Some(out) => out.clone(),
+                    None => on.map(String::from).unwrap_or_default(),
+                };
+                transitions
+                    .entry((src_idx, on))
+                    .or_default()
+                    .insert((dest_idx, out));
+            }
+        }
+    }
+
+    Ok(Transducer {
+        name: yaml_dfa.name,
+        description: yaml_dfa.description,
+        transitions,
+        start_state,
+        accept_states,
+        state_keys: state_bimap,
+    })
+}
+
+/// Parses a symbolic automaton, whose alphabet and transitions are kept as character intervals
+/// instead of being expanded into individual characters. NFA specs are determinized over
+/// intervals, failing with a [`LimitExceeded`] error if the result outgrows `limits`.
+pub fn symbolic_from_yaml(yaml_content: &str, limits: &DeterminizeLimits) -> Result<SymbolicDfa> {
+    let yaml_dfa: YamlDFA = serde_yaml::from_str(yaml_content)?;
+
+    let mut alphabet = IntervalSet::new();
+    for spec in &yaml_dfa.alphabet {
+        alphabet = alphabet.union(&spec.to_interval_set()?);
+    }
+
+    let state_bimap: BiMap<String, usize> = yaml_dfa
+        .states
+        .keys()
+        .cloned()
+        .enumerate()
+        .map(|(i, k)| (k, i))
+        .collect();
+    let start_state = get_state_idx(&state_bimap, &yaml_dfa.start_state)?;
+
+    let mut state_infos = Vec::with_capacity(yaml_dfa.states.len());
+    for (key, props) in &yaml_dfa.states {
+        if props.output.is_some() || props.token.is_some() || props.priority.is_some() {
+            return Err(anyhow!(
+                "State '{}': symbolic automata do not support outputs or tokens",
+                key
+            ));
+        }
+        state_infos.push(StateInfo {
+            label: props.label.clone(),
+            accept: props.accept,
+            token: None,
+            output: None,
+        });
+    }
+
+    let mut transitions: Vec<Vec<(Option<IntervalSet>, usize)>> =
+        vec![Vec::new(); state_bimap.len()];
+    for (src_key, mappings) in &yaml_dfa.transitions {
+        let src_idx = get_state_idx(&state_bimap, src_key)?;
+        for mapping in mappings {
+            if mapping.output.is_some() || mapping.out.is_some() {
+                return Err(anyhow!(
+                    "Symbolic automata do not support transition outputs (state '{}')",
+                    src_key
+                ));
+            }
+            let dest_idx = get_state_idx(&state_bimap, &mapping.to)?;
+            let symbols = match mapping.on.to_interval_trigger(&alphabet)? {
+                IntervalTrigger::Symbols(symbols) => Some(symbols),
+                IntervalTrigger::Epsilon if yaml_dfa.dfa => {
+                    return Err(anyhow!(
+                        "Epsilon transitions are not allowed when 'dfa' flag is true. (state '{}')",
+                        src_key
+                    ));
+                }
+                IntervalTrigger::Epsilon => None,
+            };
+            transitions[src_idx].push((symbols, dest_idx));
+        }
+    }
+
+    if yaml_dfa.dfa {
+        let transitions = transitions
+            .into_iter()
+            .map(|mappings| {
+                mappings
+                    .into_iter()
+                    .filter_map(|(symbols, dest)| symbols.map(|symbols| (symbols, dest)))
+                    .collect()
+            })
+            .collect();
+        SymbolicDfa::new(
+            yaml_dfa.name,
+            yaml_dfa.description,
+            alphabet,
+            state_bimap,
+            start_state,
+            state_infos,
+            transitions,
+        )
+    } else {
+        let nfa = SymbolicNfa {
+            transitions,
+            start_state,
+            accept_states: (0..state_infos.len())
+                .filter(|&i| state_infos[i].accept)
+                .collect(),
+            state_keys: state_bimap,
+        };
+        nfa.to_dfa(&yaml_dfa.name, yaml_dfa.description, &alphabet, limits)
     }
 }
 
@@ -531,24 +1073,25 @@ fn read_alphabet(yaml_alphabet: &[YamlSymbolSpecifier]) -> Result<BTreeSet<char>
     Ok(alphabet)
 }
 
+/// Builds the transition table of a deterministic spec, with one column per class of symbols that
+/// appear in exactly the same transitions.
 fn build_dfa_transitions(
     state_bimap: &BiMap<String, usize>,
     transitions: BTreeMap<String, Vec<YamlTransitionMapping>>,
     full_alphabet_set: &BTreeSet<char>,
     alphabet_bimap: &BiMap<char, usize>,
-) -> Result<Vec<usize>> {
+) -> Result<CompressedTable> {
     let state_count = state_bimap.len();
     let alphabet_size = alphabet_bimap.len();
 
-    let mut transition_table = vec![None; state_count * alphabet_size];
-
-    for (src_state_key, mappings) in transitions {
-        let src_idx = get_state_idx(state_bimap, &src_state_key)?;
+    // (source state key, mapping, source index, destination index, alphabet indices)
+    let mut triggers = Vec::new();
+    for (src_state_key, mappings) in &transitions {
+        let src_idx = get_state_idx(state_bimap, src_state_key)?;
 
         for mapping in mappings {
             let dest_idx = get_state_idx(state_bimap, &mapping.to)?;
 
-            // let on_chars = mapping.on.to_transition_trigger(full_alphabet_set)?;
             match mapping.on.to_transition_trigger(full_alphabet_set)? {
                 TransitionTrigger::Epsilon => {
                     return Err(anyhow!(
@@ -557,62 +1100,111 @@ fn build_dfa_transitions(
                     ));
                 }
                 TransitionTrigger::Chars(on_chars) => {
-                    for c in on_chars {
-                        let alpha_idx = get_alphabet_idx(alphabet_bimap, c)?;
-
-                        let table_idx = src_idx * alphabet_size + alpha_idx;
-
-                        match transition_table[table_idx] {
-                            Some(existing_dest_idx) => {
-                                if existing_dest_idx != dest_idx {
-                                    // AMBIGUITY ERROR: Two different transitions exist for same (state, symbol) pair.
-                                    let err_state = "ERR_STATE".to_string();
-                                    let existing_dest_key = state_bimap
-                                        .get_by_right(&existing_dest_idx)
-                                        .unwrap_or(&err_state);
-
-                                    return Err(anyhow!(
-                                        "Ambiguous transition in state '{}' for symbol '{}': \
-                                         maps to both '{}' and '{}'",
-                                        src_state_key,
-                                        c,
-                                        existing_dest_key,
-                                        mapping.to
-                                    ));
-                                }
-                            }
-                            None => {
-                                transition_table[table_idx] = Some(dest_idx);
-                            }
-                        }
+                    let alpha_indices = on_chars
+                        .iter()
+                        .map(|&c| get_alphabet_idx(alphabet_bimap, c))
+                        .collect::<Result<Vec<usize>>>()?;
+                    triggers.push((src_state_key, mapping, src_idx, dest_idx, alpha_indices));
+                }
+            }
+        }
+    }
+
+    // symbols appearing in the same transitions share a class
+    let mut refiner = ClassRefiner::new(alphabet_size);
+    for (.., alpha_indices) in &triggers {
+        refiner.refine(alpha_indices.iter().map(|&alpha_idx| (alpha_idx, ())));
+    }
+    let (char_classes, class_count) = refiner.finish();
+
+    let symbol = |alpha_idx: usize| *alphabet_bimap.get_by_right(&alpha_idx).unwrap_or(&'?');
+
+    let mut class_table = vec![None; state_count * class_count];
+    let mut class_outputs: Vec<Option<String>> = vec![None; state_count * class_count];
+
+    for (src_state_key, mapping, src_idx, dest_idx, alpha_indices) in &triggers {
+        for &alpha_idx in alpha_indices {
+            let table_idx = src_idx * class_count + char_classes[alpha_idx];
+
+            match class_table[table_idx] {
+                Some(existing_dest_idx) => {
+                    if existing_dest_idx != *dest_idx {
+                        // AMBIGUITY ERROR: Two different transitions exist for same (state, symbol) pair.
+                        let err_state = "ERR_STATE".to_string();
+                        let existing_dest_key = state_bimap
+                            .get_by_right(&existing_dest_idx)
+                            .unwrap_or(&err_state);
+
+                        return Err(anyhow!(
+                            "Ambiguous transition in state '{}' for symbol '{}': \
+                             maps to both '{}' and '{}'",
+                            src_state_key,
+                            symbol(alpha_idx),
+                            existing_dest_key,
+                            mapping.to
+                        ));
+                    }
+                }
+                None => {
+                    class_table[table_idx] = Some(*dest_idx);
+                }
+            }
+
+            if let Some(output) = &mapping.output {
+                match &class_outputs[table_idx] {
+                    Some(existing) if existing != output => {
+                        return Err(anyhow!(
+                            "Conflicting outputs in state '{}' for symbol '{}': \
+                             both '{}' and '{}'",
+                            src_state_key,
+                            symbol(alpha_idx),
+                            existing,
+                            output
+                        ));
                     }
+                    _ => class_outputs[table_idx] = Some(output.clone()),
                 }
             }
         }
     }
 
-    let final_table = transition_table
+    // first symbol of each class, for error messages
+    let mut representatives = vec![None; class_count];
+    for (alpha_idx, &class_idx) in char_classes.iter().enumerate() {
+        representatives[class_idx].get_or_insert(alpha_idx);
+    }
+
+    let final_table = class_table
         .into_iter()
         .enumerate()
         .map(|(table_idx, dest_opt)| {
             dest_opt.ok_or_else(|| {
                 // TOTALITY ERROR: This (state, symbol) pair was never defined.
-                let src_idx = table_idx / alphabet_size;
-                let alpha_idx = table_idx % alphabet_size;
+                let src_idx = table_idx / class_count;
+                let alpha_idx = representatives[table_idx % class_count].unwrap_or(0);
 
                 let err_state = "ERR_STATE".to_string();
                 let src_key = state_bimap.get_by_right(&src_idx).unwrap_or(&err_state);
-                let symbol = alphabet_bimap.get_by_right(&alpha_idx).unwrap_or(&'?');
 
                 anyhow!(
                     "Incomplete transitions for state '{}': \
                      no transition defined for symbol '{}'",
                     src_key,
-                    symbol
+                    symbol(alpha_idx)
                 )
             })
         })
         .collect::<Result<Vec<usize>>>()?;
 
-    Ok(final_table)
+    // machines without Mealy outputs keep an empty output table
+    if class_outputs.iter().all(Option::is_none) {
+        class_outputs.clear();
+    }
+
+    Ok(classes::merge(
+        &char_classes,
+        class_count,
+        &final_table,
+        &class_outputs,
+    ))
 }
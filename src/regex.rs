@@ -0,0 +1,599 @@
+use anyhow::{Result, anyhow};
+use bimap::BiMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::parser::{DeterminizeLimits, Fsm, Nfa};
+
+/// Abstract syntax tree of a regular expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Regex {
+    /// Matches nothing. Only produced when converting an automaton with an empty language.
+    Empty,
+    /// Matches the empty string.
+    Epsilon,
+    /// Matches any single character in the set. A literal is a class of one character.
+    Class(BTreeSet<char>),
+    /// Matches any single alphabet character not in the set (`[^...]`).
+    NegatedClass(BTreeSet<char>),
+    /// Matches any single alphabet character (`.`).
+    Any,
+    Concat(Vec<Regex>),
+    Alt(Vec<Regex>),
+    Star(Box<Regex>),
+    Plus(Box<Regex>),
+    Optional(Box<Regex>),
+}
+
+impl Regex {
+    /// Parses a regular expression.
+    ///
+    /// Supported syntax: literals, concatenation, alternation (`|`), repetition (`*`, `+`, `?`),
+    /// grouping (`(...)`), character classes (`[abc]`, `[a-z]`, `[^abc]`), the wildcard `.`, and
+    /// backslash escapes for metacharacters (plus `\n`, `\t` and `\r`).
+    pub fn parse(pattern: &str) -> Result<Regex> {
+        let mut parser = RegexParser {
+            pattern,
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let regex = parser.parse_alt()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("Unmatched ')'"));
+        }
+        Ok(regex)
+    }
+
+    /// Returns every character mentioned explicitly in the expression, including class members.
+    pub fn literal_chars(&self) -> BTreeSet<char> {
+        let mut chars = BTreeSet::new();
+        self.collect_literal_chars(&mut chars);
+        chars
+    }
+
+    fn collect_literal_chars(&self, chars: &mut BTreeSet<char>) {
+        match self {
+            Regex::Empty | Regex::Epsilon | Regex::Any => {}
+            Regex::Class(set) | Regex::NegatedClass(set) => chars.extend(set),
+            Regex::Concat(parts) | Regex::Alt(parts) => {
+                for part in parts {
+                    part.collect_literal_chars(chars);
+                }
+            }
+            Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => {
+                inner.collect_literal_chars(chars)
+            }
+        }
+    }
+
+    /// Returns true if the expression matches the empty string.
+    pub fn is_nullable(&self) -> bool {
+        match self {
+            Regex::Epsilon | Regex::Star(_) | Regex::Optional(_) => true,
+            Regex::Empty | Regex::Class(_) | Regex::NegatedClass(_) | Regex::Any => false,
+            Regex::Plus(inner) => inner.is_nullable(),
+            Regex::Concat(parts) => parts.iter().all(Regex::is_nullable),
+            Regex::Alt(branches) => branches.iter().any(Regex::is_nullable),
+        }
+    }
+
+    /// Builds `first` followed by `second`, simplifying the result.
+    ///
+    /// `∅` absorbs, `ε` is dropped, nested concatenations are flattened, and `x x*` or `x* x`
+    /// become `x+`.
+    pub fn concat(first: Regex, second: Regex) -> Regex {
+        if first == Regex::Empty || second == Regex::Empty {
+            return Regex::Empty;
+        }
+
+        let mut parts: Vec<Regex> = Vec::new();
+        for part in [first, second] {
+            let items = match part {
+                Regex::Concat(items) => items,
+                other => vec![other],
+            };
+            for item in items {
+                push_concat_part(&mut parts, item);
+            }
+        }
+
+        match parts.len() {
+            0 => Regex::Epsilon,
+            1 => parts.pop().unwrap(),
+            _ => Regex::Concat(parts),
+        }
+    }
+
+    /// Builds `first | second`, simplifying the result.
+    ///
+    /// `∅` is dropped, nested alternations are flattened, duplicates are removed, single-character
+    /// branches are merged into one class, and an `ε` branch becomes a trailing `?`.
+    pub fn alt(first: Regex, second: Regex) -> Regex {
+        let mut branches: Vec<Regex> = Vec::new();
+        let mut optional = false;
+        let mut pending = vec![second, first];
+
+        while let Some(branch) = pending.pop() {
+            match branch {
+                Regex::Empty => {}
+                Regex::Epsilon => optional = true,
+                Regex::Optional(inner) => {
+                    optional = true;
+                    pending.push(*inner);
+                }
+                Regex::Alt(items) => pending.extend(items.into_iter().rev()),
+                Regex::Class(set) => {
+                    // merge into the first class branch, if any
+                    if let Some(Regex::Class(existing)) =
+                        branches.iter_mut().find(|b| matches!(b, Regex::Class(_)))
+                    {
+                        existing.extend(set);
+                    } else {
+                        branches.push(Regex::Class(set));
+                    }
+                }
+                other => {
+                    if !branches.contains(&other) {
+                        branches.push(other);
+                    }
+                }
+            }
+        }
+
+        let result = match branches.len() {
+            0 if optional => return Regex::Epsilon,
+            0 => return Regex::Empty,
+            1 => branches.pop().unwrap(),
+            _ => Regex::Alt(branches),
+        };
+
+        if optional && !result.is_nullable() {
+            Regex::Optional(Box::new(result))
+        } else {
+            result
+        }
+    }
+
+    /// Builds `inner*`, simplifying the result.
+    pub fn star(inner: Regex) -> Regex {
+        match inner {
+            Regex::Empty | Regex::Epsilon => Regex::Epsilon,
+            Regex::Star(x) | Regex::Plus(x) | Regex::Optional(x) => Regex::star(*x),
+            other => Regex::Star(Box::new(other)),
+        }
+    }
+
+    /// Writes the expression, adding parentheses where the context binds tighter.
+    ///
+    /// Precedence levels: 0 = alternation, 1 = concatenation, 2 = postfix operator, 3 = atom.
+    fn fmt_with_precedence(&self, f: &mut fmt::Formatter, precedence: u8) -> fmt::Result {
+        let (level, needs_parens) = match self {
+            Regex::Alt(_) => (0, precedence > 0),
+            Regex::Concat(_) => (1, precedence > 1),
+            Regex::Star(_) | Regex::Plus(_) | Regex::Optional(_) => (2, precedence > 2),
+            _ => (3, false),
+        };
+        if needs_parens {
+            write!(f, "(")?;
+        }
+
+        match self {
+            Regex::Empty => write!(f, "∅")?,
+            Regex::Epsilon => write!(f, "()")?,
+            Regex::Any => write!(f, ".")?,
+            Regex::Class(set) if set.len() == 1 => {
+                let c = *set.first().unwrap();
+                write!(f, "{}", escape_char(c, "()|*+?[].\\"))?;
+            }
+            Regex::Class(set) => write!(f, "[{}]", format_class(set))?,
+            Regex::NegatedClass(set) => write!(f, "[^{}]", format_class(set))?,
+            Regex::Concat(parts) => {
+                for part in parts {
+                    part.fmt_with_precedence(f, level + 1)?;
+                }
+            }
+            Regex::Alt(branches) => {
+                for (i, branch) in branches.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "|")?;
+                    }
+                    branch.fmt_with_precedence(f, level + 1)?;
+                }
+            }
+            Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => {
+                inner.fmt_with_precedence(f, level + 1)?;
+                let op = match self {
+                    Regex::Star(_) => '*',
+                    Regex::Plus(_) => '+',
+                    _ => '?',
+                };
+                write!(f, "{}", op)?;
+            }
+        }
+
+        if needs_parens {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays the expression in the syntax accepted by [`Regex::parse`]. The empty language is
+/// shown as `∅`, which has no parseable equivalent.
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with_precedence(f, 0)
+    }
+}
+
+/// Appends `item` to a flattened concatenation, folding `x x*` and `x* x` into `x+`.
+fn push_concat_part(parts: &mut Vec<Regex>, item: Regex) {
+    if item == Regex::Epsilon {
+        return;
+    }
+
+    if let Regex::Star(inner) = &item {
+        // x x* -> x+, where x may itself span several parts
+        let inner_parts: &[Regex] = match inner.as_ref() {
+            Regex::Concat(items) => items,
+            other => std::slice::from_ref(other),
+        };
+        if parts.ends_with(inner_parts) {
+            parts.truncate(parts.len() - inner_parts.len());
+            parts.push(Regex::Plus(inner.clone()));
+            return;
+        }
+        // x* x* -> x*
+        if parts.last() == Some(&item) {
+            return;
+        }
+    }
+
+    // x* x -> x+
+    if let Some(Regex::Star(inner)) = parts.last()
+        && inner.as_ref() == &item
+    {
+        let inner = inner.clone();
+        parts.pop();
+        parts.push(Regex::Plus(inner));
+        return;
+    }
+
+    parts.push(item);
+}
+
+/// Formats the members of a character class, collapsing runs of three or more into ranges.
+fn format_class(set: &BTreeSet<char>) -> String {
+    let mut result = String::new();
+    let mut iter = set.iter().peekable();
+
+    while let Some(&start) = iter.next() {
+        let mut end = start;
+        while let Some(&&next) = iter.peek() {
+            if (next as u32) == (end as u32) + 1 {
+                end = next;
+                iter.next();
+            } else {
+                break;
+            }
+        }
+
+        result.push_str(&escape_char(start, "]\\^-"));
+        if (end as u32) >= (start as u32) + 2 {
+            result.push('-');
+            result.push_str(&escape_char(end, "]\\^-"));
+        } else if end != start {
+            result.push_str(&escape_char(end, "]\\^-"));
+        }
+    }
+
+    result
+}
+
+/// Escapes a character with a backslash if it is one of `special` or a control character.
+fn escape_char(c: char, special: &str) -> String {
+    match c {
+        '\n' => "\\n".to_string(),
+        '\t' => "\\t".to_string(),
+        '\r' => "\\r".to_string(),
+        _ if special.contains(c) => format!("\\{}", c),
+        _ => c.to_string(),
+    }
+}
+
+/// Compiles a regular expression into an NFA (via Thompson's construction) and its DFA.
+///
+/// The alphabet is the set of characters mentioned in the pattern, so `.` and negated classes
+/// only match those characters. Use [`from_regex_with_alphabet`] to widen it.
+pub fn from_regex(pattern: &str) -> Result<Fsm> {
+    from_regex_with_alphabet(pattern, &BTreeSet::new())
+}
+
+/// Compiles a regular expression like [`from_regex`], adding `extra_alphabet` to the characters
+/// mentioned in the pattern to form the alphabet.
+pub fn from_regex_with_alphabet(pattern: &str, extra_alphabet: &BTreeSet<char>) -> Result<Fsm> {
+    from_regex_with_limits(pattern, extra_alphabet, &DeterminizeLimits::default())
+}
+
+/// Compiles a regular expression like [`from_regex_with_alphabet`], failing with a
+/// [`LimitExceeded`](crate::parser::LimitExceeded) error if determinizing it exceeds `limits`.
+pub fn from_regex_with_limits(
+    pattern: &str,
+    extra_alphabet: &BTreeSet<char>,
+    limits: &DeterminizeLimits,
+) -> Result<Fsm> {
+    let regex = Regex::parse(pattern)?;
+
+    let mut alphabet = regex.literal_chars();
+    alphabet.extend(extra_alphabet);
+
+    let mut builder = ThompsonBuilder::default();
+    let (start, accept) = builder.build(&regex, &alphabet);
+    let nfa = builder.into_nfa(start, accept);
+
+    let dfa = nfa.to_dfa(
+        pattern,
+        Some(format!(
+            "Compiled from the regular expression '{}'",
+            pattern
+        )),
+        &alphabet,
+        limits,
+    )?;
+    Ok(Fsm::Nfa { nfa, dfa })
+}
+
+/// Recursive-descent parser over the characters of a pattern.
+struct RegexParser<'a> {
+    pattern: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl RegexParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        anyhow!(
+            "{} at position {} in regex '{}'",
+            message,
+            self.pos,
+            self.pattern
+        )
+    }
+
+    /// alt := concat ('|' concat)*
+    fn parse_alt(&mut self) -> Result<Regex> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            branches.push(self.parse_concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Regex::Alt(branches)
+        })
+    }
+
+    /// concat := repeat*
+    fn parse_concat(&mut self) -> Result<Regex> {
+        let mut parts = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            parts.push(self.parse_repeat()?);
+        }
+        Ok(match parts.len() {
+            0 => Regex::Epsilon,
+            1 => parts.pop().unwrap(),
+            _ => Regex::Concat(parts),
+        })
+    }
+
+    /// repeat := atom ('*' | '+' | '?')*
+    fn parse_repeat(&mut self) -> Result<Regex> {
+        let mut atom = self.parse_atom()?;
+        while let Some(c) = self.peek() {
+            atom = match c {
+                '*' => Regex::Star(Box::new(atom)),
+                '+' => Regex::Plus(Box::new(atom)),
+                '?' => Regex::Optional(Box::new(atom)),
+                _ => break,
+            };
+            self.pos += 1;
+        }
+        Ok(atom)
+    }
+
+    /// atom := '(' alt ')' | '[' class ']' | '.' | '\' escape | literal
+    fn parse_atom(&mut self) -> Result<Regex> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("Unexpected end of pattern"))?;
+        match c {
+            '(' => {
+                self.pos += 1;
+                let inner = self.parse_alt()?;
+                if self.peek() != Some(')') {
+                    return Err(self.error("Missing ')'"));
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            '[' => {
+                self.pos += 1;
+                self.parse_class()
+            }
+            '.' => {
+                self.pos += 1;
+                Ok(Regex::Any)
+            }
+            '*' | '+' | '?' => Err(self.error(&format!("Nothing to repeat before '{}'", c))),
+            '\\' => {
+                self.pos += 1;
+                let escaped = self.parse_escape()?;
+                Ok(Regex::Class(BTreeSet::from([escaped])))
+            }
+            _ => {
+                self.pos += 1;
+                Ok(Regex::Class(BTreeSet::from([c])))
+            }
+        }
+    }
+
+    /// Parses the character following a backslash.
+    fn parse_escape(&mut self) -> Result<char> {
+        let c = self.peek().ok_or_else(|| self.error("Dangling '\\'"))?;
+        self.pos += 1;
+        Ok(match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            _ => c,
+        })
+    }
+
+    /// Parses a character class after the opening '['.
+    fn parse_class(&mut self) -> Result<Regex> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut set = BTreeSet::new();
+        let mut first = true;
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("Missing ']'"))?;
+            // a ']' directly after the opening bracket is a literal
+            if c == ']' && !first {
+                self.pos += 1;
+                break;
+            }
+            first = false;
+
+            let start = self.parse_class_char()?;
+            let is_range = self.peek() == Some('-')
+                && self
+                    .chars
+                    .get(self.pos + 1)
+                    .is_some_and(|&next| next != ']');
+            if is_range {
+                self.pos += 1;
+                let end = self.parse_class_char()?;
+                if start > end {
+                    return Err(self.error(&format!("Invalid class range '{}-{}'", start, end)));
+                }
+                set.extend(start..=end);
+            } else {
+                set.insert(start);
+            }
+        }
+
+        Ok(if negated {
+            Regex::NegatedClass(set)
+        } else {
+            Regex::Class(set)
+        })
+    }
+
+    fn parse_class_char(&mut self) -> Result<char> {
+        let c = self.peek().ok_or_else(|| self.error("Missing ']'"))?;
+        self.pos += 1;
+        if c == '\\' {
+            self.parse_escape()
+        } else {
+            Ok(c)
+        }
+    }
+}
+
+/// Builds an epsilon-NFA from a regex AST using Thompson's construction.
+#[derive(Default)]
+struct ThompsonBuilder {
+    transitions: BTreeMap<(usize, Option<char>), BTreeSet<usize>>,
+    state_count: usize,
+}
+
+impl ThompsonBuilder {
+    fn new_state(&mut self) -> usize {
+        self.state_count += 1;
+        self.state_count - 1
+    }
+
+    fn add_transition(&mut self, from: usize, on: Option<char>, to: usize) {
+        self.transitions.entry((from, on)).or_default().insert(to);
+    }
+
+    /// Builds the fragment for `regex` and returns its (start, accept) states.
+    fn build(&mut self, regex: &Regex, alphabet: &BTreeSet<char>) -> (usize, usize) {
+        let start = self.new_state();
+        let accept = self.new_state();
+
+        match regex {
+            Regex::Empty => {}
+            Regex::Epsilon => self.add_transition(start, None, accept),
+            Regex::Class(set) => {
+                for &c in set {
+                    self.add_transition(start, Some(c), accept);
+                }
+            }
+            Regex::NegatedClass(set) => {
+                for &c in alphabet.difference(set) {
+                    self.add_transition(start, Some(c), accept);
+                }
+            }
+            Regex::Any => {
+                for &c in alphabet {
+                    self.add_transition(start, Some(c), accept);
+                }
+            }
+            Regex::Concat(parts) => {
+                let mut prev = start;
+                for part in parts {
+                    let (part_start, part_accept) = self.build(part, alphabet);
+                    self.add_transition(prev, None, part_start);
+                    prev = part_accept;
+                }
+                self.add_transition(prev, None, accept);
+            }
+            Regex::Alt(branches) => {
+                for branch in branches {
+                    let (branch_start, branch_accept) = self.build(branch, alphabet);
+                    self.add_transition(start, None, branch_start);
+                    self.add_transition(branch_accept, None, accept);
+                }
+            }
+            Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => {
+                let (inner_start, inner_accept) = self.build(inner, alphabet);
+                self.add_transition(start, None, inner_start);
+                self.add_transition(inner_accept, None, accept);
+                if !matches!(regex, Regex::Plus(_)) {
+                    self.add_transition(start, None, accept);
+                }
+                if !matches!(regex, Regex::Optional(_)) {
+                    self.add_transition(inner_accept, None, inner_start);
+                }
+            }
+        }
+
+        (start, accept)
+    }
+
+    fn into_nfa(self, start: usize, accept: usize) -> Nfa {
+        let nfa_state_keys: BiMap<String, usize> = (0..self.state_count)
+            .map(|i| (format!("s{}", i), i))
+            .collect();
+
+        Nfa {
+            transitions: self.transitions,
+            start_state: start,
+            nfa_accept_states: BTreeSet::from([accept]),
+            nfa_state_keys,
+            nfa_tokens: BTreeMap::new(),
+        }
+    }
+}
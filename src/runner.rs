@@ -0,0 +1,73 @@
+use crate::dfa::Dfa;
+
+/// An incremental runner that feeds input to a DFA piece by piece.
+///
+/// The runner keeps the current state between calls, so input that arrives in chunks (e.g. from
+/// network reads) can be processed as it comes in and the state queried at any point. Once a
+/// character outside the alphabet is fed, the input is rejected for good: the runner has no
+/// current state until it is reset.
+#[derive(Debug, Clone)]
+pub struct DfaRunner<'a> {
+    dfa: &'a Dfa,
+    /// `None` once a character outside the alphabet has been fed.
+    current_state: Option<usize>,
+}
+
+impl<'a> DfaRunner<'a> {
+    /// Creates a runner positioned at the start state of `dfa`.
+    pub fn new(dfa: &'a Dfa) -> Self {
+        DfaRunner {
+            dfa,
+            current_state: Some(dfa.start_state_idx),
+        }
+    }
+
+    /// Consumes a single character.
+    pub fn feed(&mut self, c: char) {
+        if let Some(state) = self.current_state {
+            self.current_state = self.dfa.next_state(state, c);
+        }
+    }
+
+    /// Consumes every character of `chunk`.
+    pub fn feed_str(&mut self, chunk: &str) {
+        for c in chunk.chars() {
+            self.feed(c);
+        }
+    }
+
+    /// Returns true if the input fed so far is accepted.
+    pub fn is_accepting(&self) -> bool {
+        self.current_state
+            .is_some_and(|state| self.dfa.accept_states[state])
+    }
+
+    /// Returns true if no continuation of the input fed so far can be accepted, i.e. the runner is
+    /// in a trap state or a character outside the alphabet was fed.
+    pub fn is_trapped(&self) -> bool {
+        self.current_state
+            .is_none_or(|state| self.dfa.trap_states[state])
+    }
+
+    /// Returns the index of the current state, or `None` if a character outside the alphabet was
+    /// fed.
+    ///
+    /// Transitions are still followed after a trap state is reached, so the state may move on to
+    /// other trap states (or become `None`) as more input is fed; use [`DfaRunner::is_trapped`] to
+    /// stop feeding early.
+    pub fn current_state(&self) -> Option<usize> {
+        self.current_state
+    }
+
+    /// Returns to the start state, discarding all input fed so far.
+    pub fn reset(&mut self) {
+        self.current_state = Some(self.dfa.start_state_idx);
+    }
+}
+
+impl Dfa {
+    /// Returns a runner for feeding input to the DFA incrementally.
+    pub fn runner(&self) -> DfaRunner<'_> {
+        DfaRunner::new(self)
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Result, anyhow};
+use rand::Rng;
+
+use crate::dfa::Dfa;
+
+/// Draws accepted strings of a fixed length uniformly at random.
+///
+/// Construction precomputes, for every state and remaining length, the number of accepting paths
+/// through the transition table. Each sample then picks every next symbol with probability
+/// proportional to the number of accepted completions it leaves, which makes every accepted
+/// string of the requested length equally likely.
+pub struct Sampler<'a> {
+    dfa: &'a Dfa,
+    len: usize,
+    /// alphabet index -> char
+    symbols: Vec<char>,
+    // counts[k][state] = number of strings of length k leading from state to an accept state
+    counts: Vec<Vec<u128>>,
+}
+
+impl<'a> Sampler<'a> {
+    /// Creates a sampler for accepted strings of exactly `len` characters.
+    ///
+    /// Fails if the DFA accepts no strings of that length, or if the number of accepted strings
+    /// does not fit in a `u128` (in which case uniform sampling cannot be guaranteed).
+    pub fn new(dfa: &'a Dfa, len: usize) -> Result<Self> {
+        let state_count = dfa.state_keys.len();
+        let alphabet_size = dfa.alphabet.len();
+
+        let mut symbols = vec!['\0'; alphabet_size];
+        for (&c, &idx) in dfa.alphabet.iter() {
+            symbols[idx] = c;
+        }
+
+        // counts saturate at u128::MAX. every count used while sampling is at most the start
+        // state's count, and a saturated count makes every sum it is part of saturate, so the
+        // counts are exact wherever they are used unless the start state's count saturated
+        let mut counts: Vec<Vec<u128>> = Vec::with_capacity(len + 1);
+        counts.push(dfa.accept_states.iter().map(|&a| u128::from(a)).collect());
+        for k in 1..=len {
+            let row = (0..state_count)
+                .map(|src| {
+                    (0..alphabet_size).fold(0u128, |total, alpha_idx| {
+                        total.saturating_add(counts[k - 1][dfa.transition(src, alpha_idx)])
+                    })
+                })
+                .collect();
+            counts.push(row);
+        }
+
+        match counts[len][dfa.start_state_idx] {
+            0 => return Err(anyhow!("No accepted strings of length {}", len)),
+            u128::MAX => {
+                return Err(anyhow!(
+                    "Too many accepted strings of length {} to sample uniformly",
+                    len
+                ));
+            }
+            _ => {}
+        }
+
+        Ok(Sampler {
+            dfa,
+            len,
+            symbols,
+            counts,
+        })
+    }
+
+    /// Returns the number of accepted strings of the sampled length.
+    pub fn count(&self) -> u128 {
+        self.counts[self.len][self.dfa.start_state_idx]
+    }
+
+    /// Draws one accepted string uniformly at random.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        let mut result = String::with_capacity(self.len);
+        let mut state = self.dfa.start_state_idx;
+
+        for remaining in (0..self.len).rev() {
+            // pick the r-th accepted completion, walking symbols in alphabet order
+            let mut r = rng.random_range(0..self.counts[remaining + 1][state]);
+            for (alpha_idx, &symbol) in self.symbols.iter().enumerate() {
+                let dest = self.dfa.transition(state, alpha_idx);
+                let paths = self.counts[remaining][dest];
+                if r < paths {
+                    result.push(symbol);
+                    state = dest;
+                    break;
+                }
+                r -= paths;
+            }
+        }
+
+        result
+    }
+}
+
+impl Dfa {
+    /// Returns a sampler drawing accepted strings of exactly `len` characters uniformly at random.
+    pub fn sampler(&self, len: usize) -> Result<Sampler<'_>> {
+        Sampler::new(self, len)
+    }
+}
@@ -0,0 +1,154 @@
+use crate::dfa::Dfa;
+
+/// How to choose between the matches that start at the leftmost possible position.
+///
+/// In a backtracking regex engine, leftmost-first picks the match of the first alternative in
+/// pattern order. A DFA has no alternatives to prioritize, so here leftmost-first reports the
+/// first match found while scanning from the leftmost position, which is the shortest one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// Report the longest match starting at the leftmost position.
+    LeftmostLongest,
+    /// Report the first match found from the leftmost position, i.e. the one ending earliest.
+    LeftmostFirst,
+}
+
+/// A substring of the searched text that is in the DFA's language.
+///
+/// Spans are half-open and given both in bytes (for slicing the text) and in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+impl Match {
+    /// Returns the matched substring of `text`, which must be the searched text.
+    pub fn as_str<'t>(&self, text: &'t str) -> &'t str {
+        &text[self.start..self.end]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// An iterator over the non-overlapping matches of a DFA's language in a text, from left to
+/// right. Created by [`Dfa::find_iter`].
+///
+/// Characters that are not in the alphabet cannot be part of a match, so they act as match
+/// boundaries. If the DFA accepts the empty string, empty matches are reported as well, but never
+/// twice at the same position, nor right where the previous match ended.
+pub struct Matches<'d, 't> {
+    dfa: &'d Dfa,
+    text: &'t str,
+    kind: MatchKind,
+    /// Byte offset where the search for the next match starts.
+    pos: usize,
+    /// Character offset corresponding to `pos`.
+    char_pos: usize,
+    /// Byte offset where the previously reported match ended.
+    last_end: Option<usize>,
+}
+
+impl Iterator for Matches<'_, '_> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        while self.pos <= self.text.len() {
+            let found = self.match_at(self.pos, self.char_pos);
+
+            match found {
+                Some(m) if !m.is_empty() => {
+                    self.pos = m.end;
+                    self.char_pos = m.char_end;
+                }
+                // an empty match (or none) moves the search one character ahead
+                _ => self.advance_one_char(),
+            }
+
+            if let Some(m) = found {
+                if m.is_empty() && self.last_end == Some(m.start) {
+                    continue;
+                }
+                self.last_end = Some(m.end);
+                return found;
+            }
+        }
+
+        None
+    }
+}
+
+impl Matches<'_, '_> {
+    /// Returns the match starting exactly at the given offsets, if any.
+    fn match_at(&self, start: usize, char_start: usize) -> Option<Match> {
+        let dfa = self.dfa;
+        let mut state = dfa.start_state_idx;
+        let at = |end, char_end| Match {
+            start,
+            end,
+            char_start,
+            char_end,
+        };
+
+        let mut found = dfa.accept_states[state].then(|| at(start, char_start));
+        if found.is_some() && self.kind == MatchKind::LeftmostFirst {
+            return found;
+        }
+
+        for (char_offset, (byte_offset, c)) in self.text[start..].char_indices().enumerate() {
+            // characters outside the alphabet end every match
+            let Some(next_state) = dfa.next_state(state, c) else {
+                break;
+            };
+            state = next_state;
+            if dfa.trap_states[state] {
+                break;
+            }
+
+            if dfa.accept_states[state] {
+                found = Some(at(
+                    start + byte_offset + c.len_utf8(),
+                    char_start + char_offset + 1,
+                ));
+                if self.kind == MatchKind::LeftmostFirst {
+                    break;
+                }
+            }
+        }
+
+        found
+    }
+
+    fn advance_one_char(&mut self) {
+        match self.text[self.pos..].chars().next() {
+            Some(c) => self.pos += c.len_utf8(),
+            // past the end of the text, which stops the iteration
+            None => self.pos += 1,
+        }
+        self.char_pos += 1;
+    }
+}
+
+impl Dfa {
+    /// Returns an iterator over the non-overlapping substrings of `text` that are in the DFA's
+    /// language, from left to right.
+    pub fn find_iter<'d, 't>(&'d self, text: &'t str, kind: MatchKind) -> Matches<'d, 't> {
+        Matches {
+            dfa: self,
+            text,
+            kind,
+            pos: 0,
+            char_pos: 0,
+            last_end: None,
+        }
+    }
+
+    /// Returns the leftmost substring of `text` that is in the DFA's language, if any.
+    pub fn find(&self, text: &str, kind: MatchKind) -> Option<Match> {
+        self.find_iter(text, kind).next()
+    }
+}
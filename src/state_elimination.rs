@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::dfa::Dfa;
+use crate::parser::Nfa;
+use crate::regex::Regex;
+
+impl Dfa {
+    /// Converts the DFA to an equivalent regular expression using state elimination.
+    ///
+    /// Minimizing the DFA first usually gives a considerably shorter expression.
+    pub fn to_regex(&self) -> Regex {
+        let mut chars_by_edge: BTreeMap<(usize, usize), BTreeSet<char>> = BTreeMap::new();
+        for (&c, &alpha_idx) in self.alphabet.iter() {
+            for src in 0..self.state_keys.len() {
+                let dest = self.transition(src, alpha_idx);
+                chars_by_edge.entry((src, dest)).or_default().insert(c);
+            }
+        }
+
+        let edges = chars_by_edge
+            .into_iter()
+            .map(|(edge, chars)| (edge, Regex::Class(chars)))
+            .collect();
+        let accepting: Vec<usize> = (0..self.state_keys.len())
+            .filter(|&s| self.accept_states[s])
+            .collect();
+
+        eliminate_states(
+            self.state_keys.len(),
+            edges,
+            self.start_state_idx,
+            &accepting,
+        )
+    }
+}
+
+impl Nfa {
+    /// Converts the NFA to an equivalent regular expression using state elimination, without
+    /// determinizing it first. Epsilon transitions become `ε` edges.
+    pub fn to_regex(&self) -> Regex {
+        let mut edges: BTreeMap<(usize, usize), Regex> = BTreeMap::new();
+        for (&(src, on), dests) in &self.transitions {
+            let label = match on {
+                Some(c) => Regex::Class(BTreeSet::from([c])),
+                None => Regex::Epsilon,
+            };
+            for &dest in dests {
+                let existing = edges.remove(&(src, dest)).unwrap_or(Regex::Empty);
+                edges.insert((src, dest), Regex::alt(existing, label.clone()));
+            }
+        }
+
+        let accepting: Vec<usize> = self.nfa_accept_states.iter().cloned().collect();
+        eliminate_states(
+            self.nfa_state_keys.len(),
+            edges,
+            self.start_state,
+            &accepting,
+        )
+    }
+}
+
+/// Runs state elimination on a generalized NFA whose edges are labeled with regexes.
+///
+/// A fresh start state and a fresh final state are added, states that are not both reachable and
+/// co-reachable are dropped, and the remaining states are eliminated one at a time (cheapest
+/// first) until only the edge from the fresh start to the fresh final state is left.
+fn eliminate_states(
+    state_count: usize,
+    edges: BTreeMap<(usize, usize), Regex>,
+    start: usize,
+    accepting: &[usize],
+) -> Regex {
+    let initial = state_count;
+    let last = state_count + 1;
+    let total = state_count + 2;
+
+    // outgoing[src][dest] and incoming[dest] = {src}, excluding removed states
+    let mut outgoing: Vec<BTreeMap<usize, Regex>> = vec![BTreeMap::new(); total];
+    let mut incoming: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); total];
+    for ((src, dest), label) in edges {
+        add_edge(&mut outgoing, &mut incoming, src, dest, label);
+    }
+    add_edge(&mut outgoing, &mut incoming, initial, start, Regex::Epsilon);
+    for &state in accepting {
+        add_edge(&mut outgoing, &mut incoming, state, last, Regex::Epsilon);
+    }
+
+    // keep only states on some path from the fresh start to the fresh final state
+    let forward = reachable(initial, |s| outgoing[s].keys().cloned().collect());
+    let backward = reachable(last, |s| incoming[s].iter().cloned().collect());
+    let mut remaining: BTreeSet<usize> = (0..state_count)
+        .filter(|s| forward.contains(s) && backward.contains(s))
+        .collect();
+    for state in 0..state_count {
+        if !remaining.contains(&state) {
+            remove_state(&mut outgoing, &mut incoming, state);
+        }
+    }
+
+    while let Some(&state) = remaining.iter().min_by_key(|&&s| {
+        let ins = incoming[s].iter().filter(|&&i| i != s).count();
+        let outs = outgoing[s].keys().filter(|&&o| o != s).count();
+        ins * outs
+    }) {
+        remaining.remove(&state);
+
+        let self_loop = outgoing[state]
+            .get(&state)
+            .cloned()
+            .map_or(Regex::Epsilon, Regex::star);
+        let preds: Vec<usize> = incoming[state]
+            .iter()
+            .cloned()
+            .filter(|&p| p != state)
+            .collect();
+        let succs: Vec<(usize, Regex)> = outgoing[state]
+            .iter()
+            .filter(|&(&d, _)| d != state)
+            .map(|(&d, r)| (d, r.clone()))
+            .collect();
+
+        for &pred in &preds {
+            let into = outgoing[pred][&state].clone();
+            let prefix = Regex::concat(into, self_loop.clone());
+            for (succ, out) in &succs {
+                let bypass = Regex::concat(prefix.clone(), out.clone());
+                add_edge(&mut outgoing, &mut incoming, pred, *succ, bypass);
+            }
+        }
+
+        remove_state(&mut outgoing, &mut incoming, state);
+    }
+
+    outgoing[initial].remove(&last).unwrap_or(Regex::Empty)
+}
+
+/// Adds an edge, combining it by alternation with any existing edge between the same states.
+fn add_edge(
+    outgoing: &mut [BTreeMap<usize, Regex>],
+    incoming: &mut [BTreeSet<usize>],
+    src: usize,
+    dest: usize,
+    label: Regex,
+) {
+    let existing = outgoing[src].remove(&dest).unwrap_or(Regex::Empty);
+    outgoing[src].insert(dest, Regex::alt(existing, label));
+    incoming[dest].insert(src);
+}
+
+/// Removes all edges into and out of `state`.
+fn remove_state(
+    outgoing: &mut [BTreeMap<usize, Regex>],
+    incoming: &mut [BTreeSet<usize>],
+    state: usize,
+) {
+    for pred in std::mem::take(&mut incoming[state]) {
+        outgoing[pred].remove(&state);
+    }
+    for succ in std::mem::take(&mut outgoing[state]).into_keys() {
+        incoming[succ].remove(&state);
+    }
+}
+
+/// Returns the set of nodes reachable from `root` using the given neighbor function.
+fn reachable(root: usize, neighbors: impl Fn(usize) -> Vec<usize>) -> BTreeSet<usize> {
+    let mut visited = BTreeSet::from([root]);
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        for next in neighbors(node) {
+            if visited.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    visited
+}
@@ -0,0 +1,519 @@
+use anyhow::{Result, anyhow};
+use bimap::BiMap;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::analysis;
+use crate::dfa::StateInfo;
+use crate::parser::{self, DeterminizeLimits, LimitExceeded};
+
+/// A set of characters stored as sorted, disjoint, non-adjacent inclusive ranges.
+///
+/// Surrogate code points are not characters, so `'\u{D7FF}'` and `'\u{E000}'` count as adjacent
+/// and a range never starts or ends inside the surrogate block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IntervalSet {
+    ranges: Vec<(char, char)>,
+}
+
+impl IntervalSet {
+    /// Creates an empty set.
+    pub fn new() -> IntervalSet {
+        IntervalSet::default()
+    }
+
+    /// Creates the set of characters from `start` to `end`, inclusive.
+    pub fn from_range(start: char, end: char) -> IntervalSet {
+        IntervalSet::from_ranges([(start, end)])
+    }
+
+    /// Creates a set from arbitrary (possibly overlapping or empty) inclusive ranges.
+    pub fn from_ranges(ranges: impl IntoIterator<Item = (char, char)>) -> IntervalSet {
+        let mut sorted: Vec<(char, char)> = ranges.into_iter().filter(|(s, e)| s <= e).collect();
+        sorted.sort();
+
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(sorted.len());
+        for (start, end) in sorted {
+            match merged.last_mut() {
+                Some((_, last_end)) if next_char(*last_end).is_none_or(|n| start <= n) => {
+                    *last_end = (*last_end).max(end);
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+        IntervalSet { ranges: merged }
+    }
+
+    /// Returns every character, from `'\0'` to `char::MAX`.
+    pub fn full() -> IntervalSet {
+        IntervalSet::from_range('\0', char::MAX)
+    }
+
+    /// Returns the sorted, disjoint ranges of the set.
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns the smallest character in the set.
+    pub fn first(&self) -> Option<char> {
+        self.ranges.first().map(|&(start, _)| start)
+    }
+
+    /// Returns true if `c` is in the set, using a binary search over the ranges.
+    pub fn contains(&self, c: char) -> bool {
+        let idx = self.ranges.partition_point(|&(start, _)| start <= c);
+        idx > 0 && c <= self.ranges[idx - 1].1
+    }
+
+    /// Returns the number of characters in the set.
+    pub fn len(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|&(start, end)| {
+                let surrogates = if start < '\u{E000}' && end > '\u{D7FF}' {
+                    0x800
+                } else {
+                    0
+                };
+                (end as usize) - (start as usize) + 1 - surrogates
+            })
+            .sum()
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        IntervalSet::from_ranges(self.ranges.iter().chain(&other.ranges).cloned())
+    }
+
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a_start, a_end) = self.ranges[i];
+            let (b_start, b_end) = other.ranges[j];
+            let start = a_start.max(b_start);
+            let end = a_end.min(b_end);
+            if start <= end {
+                ranges.push((start, end));
+            }
+            if a_end < b_end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    /// Returns the characters that are not in the set.
+    pub fn complement(&self) -> IntervalSet {
+        let mut ranges = Vec::new();
+        let mut start = Some('\0');
+        for &(range_start, range_end) in &self.ranges {
+            if let Some(s) = start
+                && s < range_start
+            {
+                ranges.push((s, prev_char(range_start).expect("range starts after '\\0'")));
+            }
+            start = next_char(range_end);
+        }
+        if let Some(s) = start {
+            ranges.push((s, char::MAX));
+        }
+        IntervalSet { ranges }
+    }
+
+    pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        self.intersection(&other.complement())
+    }
+
+    /// Returns true if every character of the set is in `other`.
+    pub fn is_subset(&self, other: &IntervalSet) -> bool {
+        self.difference(other).is_empty()
+    }
+}
+
+/// Returns the character after `c`, skipping the surrogate block.
+fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        _ => char::from_u32(c as u32 + 1),
+    }
+}
+
+/// Returns the character before `c`, skipping the surrogate block.
+fn prev_char(c: char) -> Option<char> {
+    match c {
+        '\u{E000}' => Some('\u{D7FF}'),
+        _ => (c as u32).checked_sub(1).and_then(char::from_u32),
+    }
+}
+
+/// An NFA whose transitions are labeled with sets of characters instead of single characters.
+#[derive(Debug, Clone)]
+pub struct SymbolicNfa {
+    /// state -> (symbols, or `None` for an epsilon transition, destination state)
+    pub transitions: Vec<Vec<(Option<IntervalSet>, usize)>>,
+    pub start_state: usize,
+    pub accept_states: BTreeSet<usize>,
+    pub state_keys: BiMap<String, usize>,
+}
+
+/// A DFA whose transitions are labeled with character ranges, for alphabets far too large to
+/// have one transition table column per character (e.g. all of Unicode).
+///
+/// Every state has a sorted list of disjoint ranges covering the alphabet, each leading to a
+/// destination state. Characters outside the alphabet are rejected.
+#[derive(Debug, Clone)]
+pub struct SymbolicDfa {
+    pub name: String,
+    pub description: Option<String>,
+    pub alphabet: IntervalSet,
+    pub state_keys: BiMap<String, usize>,
+    pub start_state_idx: usize,
+    pub accept_states: Vec<bool>,
+    pub state_properties: Vec<StateInfo>,
+    /// state -> sorted (start, end, destination state) ranges
+    pub transitions: Vec<Vec<(char, char, usize)>>,
+    /// [state1_is_trap, ...]; a trap state cannot reach any accept state
+    pub trap_states: Vec<bool>,
+}
+
+impl SymbolicDfa {
+    /// Parses a symbolic automaton from a YAML string specification.
+    pub fn from_yaml(yaml_content: &str) -> Result<SymbolicDfa> {
+        parser::symbolic_from_yaml(yaml_content, &DeterminizeLimits::default())
+    }
+
+    /// Creates a symbolic DFA from the symbol sets leading from each state to its destinations.
+    ///
+    /// Fails if the sets of a state overlap with different destinations, contain characters
+    /// outside the alphabet or do not cover the whole alphabet.
+    pub fn new(
+        name: String,
+        description: Option<String>,
+        alphabet: IntervalSet,
+        state_keys: BiMap<String, usize>,
+        start_state_idx: usize,
+        state_properties: Vec<StateInfo>,
+        transitions: Vec<Vec<(IntervalSet, usize)>>,
+    ) -> Result<SymbolicDfa> {
+        let key = |idx: usize| state_keys.get_by_right(&idx).map_or("ERR", |s| s.as_str());
+
+        let mut ranges = Vec::with_capacity(transitions.len());
+        for (src_idx, mappings) in transitions.into_iter().enumerate() {
+            let mut covered = IntervalSet::new();
+            let mut row: Vec<(char, char, usize)> = Vec::new();
+            for (symbols, dest_idx) in mappings {
+                if let Some(c) = symbols.difference(&alphabet).first() {
+                    return Err(anyhow!(
+                        "Character {:?} not in alphabet (transition error in state '{}')",
+                        c,
+                        key(src_idx)
+                    ));
+                }
+                for &(start, end, existing_dest_idx) in &row {
+                    let overlap = symbols.intersection(&IntervalSet::from_range(start, end));
+                    if let Some(c) = overlap.first()
+                        && existing_dest_idx != dest_idx
+                    {
+                        return Err(anyhow!(
+                            "Ambiguous transition in state '{}' for symbol {:?}: \
+                             maps to both '{}' and '{}'",
+                            key(src_idx),
+                            c,
+                            key(existing_dest_idx),
+                            key(dest_idx)
+                        ));
+                    }
+                }
+                // only add what is new, so that the ranges of a state stay disjoint
+                let added = symbols.difference(&covered);
+                row.extend(added.ranges().iter().map(|&(s, e)| (s, e, dest_idx)));
+                covered = covered.union(&symbols);
+            }
+            if let Some(c) = alphabet.difference(&covered).first() {
+                return Err(anyhow!(
+                    "Incomplete transitions for state '{}': no transition defined for symbol {:?}",
+                    key(src_idx),
+                    c
+                ));
+            }
+            ranges.push(merge_adjacent(row));
+        }
+
+        let accept_states = state_properties.iter().map(|info| info.accept).collect();
+        let mut dfa = SymbolicDfa {
+            name,
+            description,
+            alphabet,
+            state_keys,
+            start_state_idx,
+            accept_states,
+            state_properties,
+            transitions: ranges,
+            trap_states: Vec::new(),
+        };
+        dfa.trap_states = dfa.find_trap_states();
+        Ok(dfa)
+    }
+
+    /// Runs the DFA on the given input string and returns true if accepted, false otherwise.
+    pub fn run<I>(&self, input: I) -> bool
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut current_state = self.start_state_idx;
+        for c in input {
+            match self.next_state(current_state, c) {
+                Some(next_state) if !self.trap_states[next_state] => current_state = next_state,
+                _ => return false,
+            }
+        }
+        self.accept_states[current_state]
+    }
+
+    /// Returns the state reached from `state_idx` on `c`, or `None` if `c` is not in the alphabet.
+    pub fn next_state(&self, state_idx: usize, c: char) -> Option<usize> {
+        let row = &self.transitions[state_idx];
+        let idx = row.partition_point(|&(start, _, _)| start <= c);
+        match idx.checked_sub(1).map(|i| row[i]) {
+            Some((_, end, dest)) if c <= end => Some(dest),
+            _ => None,
+        }
+    }
+
+    /// Returns the key of the state at `state_idx`.
+    pub fn state_key(&self, state_idx: usize) -> &str {
+        self.state_keys
+            .get_by_right(&state_idx)
+            .map_or("ERR", |s| s.as_str())
+    }
+
+    /// Returns the number of ranges over all states.
+    pub fn transition_count(&self) -> usize {
+        self.transitions.iter().map(Vec::len).sum()
+    }
+
+    /// Returns the symbols leading from `src_idx` to each of its destination states.
+    pub fn edges(&self, src_idx: usize) -> BTreeMap<usize, IntervalSet> {
+        let mut edges: BTreeMap<usize, Vec<(char, char)>> = BTreeMap::new();
+        for &(start, end, dest) in &self.transitions[src_idx] {
+            edges.entry(dest).or_default().push((start, end));
+        }
+        edges
+            .into_iter()
+            .map(|(dest, ranges)| (dest, IntervalSet::from_ranges(ranges)))
+            .collect()
+    }
+
+    /// Returns, for each state, whether no accept state can be reached from it.
+    fn find_trap_states(&self) -> Vec<bool> {
+        analysis::coreachable(&self.accept_states, |src| {
+            self.transitions[src].iter().map(|&(_, _, dest)| dest)
+        })
+        .iter()
+        .map(|&c| !c)
+        .collect()
+    }
+}
+
+impl SymbolicNfa {
+    /// Converts the NFA to an equivalent symbolic DFA using subset construction over intervals.
+    ///
+    /// For each DFA state, the alphabet is cut at every boundary of the ranges leaving its NFA
+    /// states, so that all characters of a piece lead to the same NFA states; pieces with the same
+    /// target are then merged. Characters with no transition lead to a `FAILURE` dead state. The
+    /// number of ranges counts towards the `max_cells` limit.
+    pub fn to_dfa(
+        &self,
+        name: &str,
+        description: Option<String>,
+        alphabet: &IntervalSet,
+        limits: &DeterminizeLimits,
+    ) -> Result<SymbolicDfa> {
+        let mut dfa_states: BTreeMap<BTreeSet<usize>, usize> = BTreeMap::new();
+        let mut worklist: VecDeque<BTreeSet<usize>> = VecDeque::new();
+        // DFA state -> (start, end, target NFA set), the empty set standing for the dead state
+        let mut rows: Vec<Vec<(char, char, BTreeSet<usize>)>> = Vec::new();
+        let mut cells = 0;
+
+        let start_set = self.epsilon_closure(BTreeSet::from([self.start_state]));
+        dfa_states.insert(start_set.clone(), 0);
+        worklist.push_back(start_set);
+
+        while let Some(current_set) = worklist.pop_front() {
+            let row = self.split_alphabet(&current_set, alphabet);
+            cells += row.len();
+
+            for (_, _, target_set) in &row {
+                if target_set.is_empty() || dfa_states.contains_key(target_set) {
+                    continue;
+                }
+                let new_idx = dfa_states.len();
+                if let Some(limit) = limits.exceeded(new_idx + 1, cells) {
+                    return Err(
+                        LimitExceeded::new(limit, new_idx, &worklist, &self.state_keys).into(),
+                    );
+                }
+                dfa_states.insert(target_set.clone(), new_idx);
+                worklist.push_back(target_set.clone());
+            }
+            rows.push(row);
+        }
+
+        // dead state for characters without a transition. equivalent to Ø state.
+        let needs_dead_state = rows.iter().flatten().any(|(_, _, set)| set.is_empty());
+        let dead_state_idx = needs_dead_state.then_some(dfa_states.len());
+        if let Some(idx) = dead_state_idx {
+            cells += alphabet.ranges().len();
+            if let Some(limit) = limits.exceeded(idx + 1, cells) {
+                return Err(LimitExceeded::new(limit, idx, &worklist, &self.state_keys).into());
+            }
+        }
+
+        let mut sorted_dfa_states: Vec<(BTreeSet<usize>, usize)> = dfa_states.into_iter().collect();
+        sorted_dfa_states.sort_by_key(|(_, idx)| *idx);
+
+        let mut state_keys = BiMap::new();
+        let mut state_properties = Vec::new();
+        for (nfa_set, dfa_idx) in &sorted_dfa_states {
+            let mut keys: Vec<&str> = nfa_set
+                .iter()
+                .filter_map(|id| self.state_keys.get_by_right(id))
+                .map(String::as_str)
+                .collect();
+            keys.sort();
+            let key = format!("{{{}}}", keys.join(","));
+            state_keys.insert(key.clone(), *dfa_idx);
+            state_properties.push(StateInfo {
+                label: Some(key),
+                accept: nfa_set.intersection(&self.accept_states).next().is_some(),
+                token: None,
+                output: None,
+            });
+        }
+
+        let index_of: BTreeMap<&BTreeSet<usize>, usize> = sorted_dfa_states
+            .iter()
+            .map(|(set, idx)| (set, *idx))
+            .collect();
+        let mut transitions: Vec<Vec<(IntervalSet, usize)>> = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|(start, end, set)| {
+                        let dest = index_of
+                            .get(set)
+                            .copied()
+                            .or(dead_state_idx)
+                            .expect("an empty target set has a dead state");
+                        (IntervalSet::from_range(*start, *end), dest)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        if let Some(idx) = dead_state_idx {
+            let key = "FAILURE".to_string();
+            state_keys.insert(key.clone(), idx);
+            state_properties.push(StateInfo {
+                label: Some(key),
+                accept: false,
+                token: None,
+                output: None,
+            });
+            transitions.push(vec![(alphabet.clone(), idx)]);
+        }
+
+        SymbolicDfa::new(
+            name.to_string(),
+            description,
+            alphabet.clone(),
+            state_keys,
+            0,
+            state_properties,
+            transitions,
+        )
+    }
+
+    /// Cuts the alphabet into ranges on which `states` all move to the same set of NFA states,
+    /// returning each range with the epsilon closure of that set. Adjacent ranges with the same
+    /// target are merged.
+    fn split_alphabet(
+        &self,
+        states: &BTreeSet<usize>,
+        alphabet: &IntervalSet,
+    ) -> Vec<(char, char, BTreeSet<usize>)> {
+        let outgoing: Vec<(&IntervalSet, usize)> = states
+            .iter()
+            .flat_map(|&s| &self.transitions[s])
+            .filter_map(|(symbols, dest)| symbols.as_ref().map(|symbols| (symbols, *dest)))
+            .collect();
+
+        // every range starts at a boundary and ends right before the next one
+        let mut boundaries: BTreeSet<char> = BTreeSet::new();
+        for set in outgoing.iter().map(|(set, _)| *set).chain([alphabet]) {
+            for &(start, end) in set.ranges() {
+                boundaries.insert(start);
+                boundaries.extend(next_char(end));
+            }
+        }
+        let boundaries: Vec<char> = boundaries.into_iter().collect();
+
+        let mut row: Vec<(char, char, BTreeSet<usize>)> = Vec::new();
+        for (i, &start) in boundaries.iter().enumerate() {
+            if !alphabet.contains(start) {
+                continue;
+            }
+            let end = boundaries.get(i + 1).map_or(char::MAX, |&next| {
+                prev_char(next).expect("boundaries are sorted")
+            });
+            let moved: BTreeSet<usize> = outgoing
+                .iter()
+                .filter(|(set, _)| set.contains(start))
+                .map(|&(_, dest)| dest)
+                .collect();
+            let target = self.epsilon_closure(moved);
+
+            match row.last_mut() {
+                Some((_, last_end, last_target))
+                    if next_char(*last_end) == Some(start) && *last_target == target =>
+                {
+                    *last_end = end;
+                }
+                _ => row.push((start, end, target)),
+            }
+        }
+        row
+    }
+
+    /// Calculates the epsilon closure of a set of NFA states.
+    fn epsilon_closure(&self, states: BTreeSet<usize>) -> BTreeSet<usize> {
+        parser::epsilon_closure(states, |state| {
+            self.transitions[state]
+                .iter()
+                .filter(|(symbols, _)| symbols.is_none())
+                .map(|&(_, dest)| dest)
+        })
+    }
+}
+
+/// Merges consecutive ranges of a sorted row that are adjacent and lead to the same state.
+fn merge_adjacent(mut row: Vec<(char, char, usize)>) -> Vec<(char, char, usize)> {
+    row.sort();
+    let mut merged: Vec<(char, char, usize)> = Vec::with_capacity(row.len());
+    for (start, end, dest) in row {
+        match merged.last_mut() {
+            Some((_, last_end, last_dest))
+                if next_char(*last_end) == Some(start) && *last_dest == dest =>
+            {
+                *last_end = end;
+            }
+            _ => merged.push((start, end, dest)),
+        }
+    }
+    merged
+}
@@ -0,0 +1,258 @@
+use anyhow::{Result, anyhow};
+use bimap::BiMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use crate::parser;
+
+/// Map from (from_state, on_char) to the set of (destination state, output) pairs.
+/// `on_char = None` represents an epsilon transition.
+pub type TransducerTransitions = BTreeMap<(usize, Option<char>), BTreeSet<(usize, String)>>;
+
+/// A nondeterministic finite-state transducer, mapping input strings to output strings.
+///
+/// Like [`parser::Nfa`], a transition reads one input character or nothing (epsilon), but it also
+/// writes an output string, which may be empty. An input is mapped to the concatenated outputs of
+/// every path that reads exactly the input and ends in an accept state, so it can have any number
+/// of outputs.
+#[derive(Debug, Clone)]
+pub struct Transducer {
+    pub name: String,
+    pub description: Option<String>,
+    pub transitions: TransducerTransitions,
+    pub start_state: usize,
+    pub accept_states: BTreeSet<usize>,
+    pub state_keys: BiMap<String, usize>,
+}
+
+/// A configuration of a run: (state, number of input characters read, output written so far).
+type Configuration = (usize, usize, String);
+
+impl Transducer {
+    /// Parses a transducer from a YAML string specification.
+    pub fn from_yaml(yaml_content: &str) -> Result<Transducer> {
+        parser::transducer_from_yaml(yaml_content)
+    }
+
+    /// Returns every output the transducer produces for `input`, in lexicographic order.
+    ///
+    /// Fails if the transducer has a cycle of epsilon transitions that writes output, since an
+    /// input could then have infinitely many outputs.
+    pub fn outputs(&self, input: &str) -> Result<BTreeSet<String>> {
+        self.check_epsilon_cycles()?;
+
+        let input: Vec<char> = input.chars().collect();
+        let mut outputs = BTreeSet::new();
+        let start: Configuration = (self.start_state, 0, String::new());
+        let mut visited: BTreeSet<Configuration> = BTreeSet::from([start.clone()]);
+        let mut queue: VecDeque<Configuration> = VecDeque::from([start]);
+
+        while let Some((state, pos, output)) = queue.pop_front() {
+            if pos == input.len() && self.accept_states.contains(&state) {
+                outputs.insert(output.clone());
+            }
+
+            let epsilon_moves = self.moves(state, None).map(|(dest, out)| (dest, pos, out));
+            let char_moves = input
+                .get(pos)
+                .into_iter()
+                .flat_map(|&c| self.moves(state, Some(c)))
+                .map(|(dest, out)| (dest, pos + 1, out));
+
+            for (dest, next_pos, out) in epsilon_moves.chain(char_moves) {
+                let next = (dest, next_pos, format!("{}{}", output, out));
+                if visited.insert(next.clone()) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    /// Returns the transducer mapping each input of `self` to the outputs `other` produces for
+    /// the outputs of `self`, i.e. running `self` and then `other` on its output.
+    ///
+    /// States of the result are the reachable pairs of a state of `self` and a state of `other`,
+    /// with keys like `(q1,p3)`. A pair is accepting if both of its states are.
+    pub fn compose(&self, other: &Transducer) -> Transducer {
+        // with at most one output character per transition, `other` can follow `self` step by step
+        let first = self.with_single_char_outputs();
+
+        let mut pair_indices: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+        let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+        let mut transitions = TransducerTransitions::new();
+
+        let start = (first.start_state, other.start_state);
+        pair_indices.insert(start, 0);
+        pairs.push(start);
+        queue.push_back(start);
+
+        while let Some((a, b)) = queue.pop_front() {
+            let src = pair_indices[&(a, b)];
+            let mut moves: Vec<(Option<char>, (usize, usize), String)> = Vec::new();
+
+            for (&(_, on), dests) in first.transitions.range((a, None)..=(a, Some(char::MAX))) {
+                for (dest_a, out) in dests {
+                    match out.chars().next() {
+                        // `self` writes nothing, so `other` does not move
+                        None => moves.push((on, (*dest_a, b), String::new())),
+                        // `other` reads the character written by `self`
+                        Some(c) => {
+                            for (dest_b, out_b) in other.moves(b, Some(c)) {
+                                moves.push((on, (*dest_a, dest_b), out_b.to_string()));
+                            }
+                        }
+                    }
+                }
+            }
+            // `other` can also take epsilon transitions on its own
+            for (dest_b, out_b) in other.moves(b, None) {
+                moves.push((None, (a, dest_b), out_b.to_string()));
+            }
+
+            for (on, next, out) in moves {
+                let dest = *pair_indices.entry(next).or_insert_with(|| {
+                    pairs.push(next);
+                    queue.push_back(next);
+                    pairs.len() - 1
+                });
+                transitions
+                    .entry((src, on))
+                    .or_default()
+                    .insert((dest, out));
+            }
+        }
+
+        let mut state_keys = BiMap::new();
+        let mut accept_states = BTreeSet::new();
+        for (idx, &(a, b)) in pairs.iter().enumerate() {
+            let key = format!("({},{})", first.state_key(a), other.state_key(b));
+            state_keys.insert(key, idx);
+            if first.accept_states.contains(&a) && other.accept_states.contains(&b) {
+                accept_states.insert(idx);
+            }
+        }
+
+        Transducer {
+            name: format!("{} ∘ {}", other.name, self.name),
+            description: Some(format!(
+                "Composition of '{}' followed by '{}'",
+                self.name, other.name
+            )),
+            transitions,
+            start_state: 0,
+            accept_states,
+            state_keys,
+        }
+    }
+
+    /// Returns the key of the state at `state_idx`.
+    pub fn state_key(&self, state_idx: usize) -> &str {
+        self.state_keys
+            .get_by_right(&state_idx)
+            .map_or("ERR", |s| s.as_str())
+    }
+
+    /// Returns the (destination, output) pairs of the transitions from `state` on `on`.
+    fn moves(&self, state: usize, on: Option<char>) -> impl Iterator<Item = (usize, &str)> {
+        self.transitions
+            .get(&(state, on))
+            .into_iter()
+            .flatten()
+            .map(|(dest, out)| (*dest, out.as_str()))
+    }
+
+    /// Returns an equivalent transducer whose transitions write at most one character each.
+    ///
+    /// A transition writing `n > 1` characters is split into a chain through `n - 1` new states,
+    /// the first step reading the input and writing the first character and the remaining steps
+    /// being epsilon transitions writing one character each. New states are named
+    /// `src->dest#idx`, primed (e.g. `a->b#3'`) if a state of that name already exists.
+    fn with_single_char_outputs(&self) -> Transducer {
+        let mut result = self.clone();
+        result.transitions.clear();
+        let mut next_state = self.state_keys.len();
+
+        for (&(src, on), dests) in &self.transitions {
+            for (dest, out) in dests {
+                let chars: Vec<char> = out.chars().collect();
+                if chars.len() <= 1 {
+                    result
+                        .transitions
+                        .entry((src, on))
+                        .or_default()
+                        .insert((*dest, out.clone()));
+                    continue;
+                }
+
+                let mut from = src;
+                let mut from_on = on;
+                for (i, c) in chars.iter().enumerate() {
+                    let to = if i + 1 == chars.len() {
+                        *dest
+                    } else {
+                        let mut key = format!(
+                            "{}->{}#{}",
+                            self.state_key(src),
+                            self.state_key(*dest),
+                            next_state
+                        );
+                        // a user state may already be named like this; generated keys end in
+                        // the (unique) state index, so primed keys cannot collide with them
+                        while result.state_keys.contains_left(&key) {
+                            key.push('\'');
+                        }
+                        result.state_keys.insert(key, next_state);
+                        next_state += 1;
+                        next_state - 1
+                    };
+                    result
+                        .transitions
+                        .entry((from, from_on))
+                        .or_default()
+                        .insert((to, c.to_string()));
+                    from = to;
+                    from_on = None;
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Fails if some cycle of epsilon transitions writes output.
+    fn check_epsilon_cycles(&self) -> Result<()> {
+        // an epsilon transition writing output is on a cycle iff its source is reachable from its
+        // destination using epsilon transitions only
+        for (&(src, on), dests) in &self.transitions {
+            if on.is_some() {
+                continue;
+            }
+            for (dest, out) in dests {
+                if !out.is_empty() && self.epsilon_reachable(*dest).contains(&src) {
+                    return Err(anyhow!(
+                        "Transducer '{}' has an epsilon cycle through '{}' that writes output",
+                        self.name,
+                        self.state_key(src)
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the states reachable from `state` using epsilon transitions only.
+    fn epsilon_reachable(&self, state: usize) -> BTreeSet<usize> {
+        let mut reachable = BTreeSet::from([state]);
+        let mut stack = vec![state];
+        while let Some(s) = stack.pop() {
+            for (dest, _) in self.moves(s, None) {
+                if reachable.insert(dest) {
+                    stack.push(dest);
+                }
+            }
+        }
+        reachable
+    }
+}
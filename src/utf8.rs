@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use crate::analysis;
+use crate::dfa::Dfa;
+
+/// A DFA over UTF-8 bytes, equivalent to a DFA over `char`s.
+///
+/// Every character transition of the original DFA is expanded into the byte sequences that
+/// encode it, through intermediate states that are shared between transitions ending in the same
+/// way. Bytes that cannot continue a valid UTF-8 encoding of an alphabet character lead to a dead
+/// state, so running the automaton also validates the input: overlong encodings, surrogates and
+/// truncated sequences are rejected.
+#[derive(Debug, Clone)]
+pub struct ByteDfa {
+    pub name: String,
+    pub description: Option<String>,
+
+    /// States `0..char_state_count` are the states of the original DFA, with the same indices.
+    /// They are followed by the intermediate states and the dead state.
+    pub char_state_count: usize,
+    pub start_state_idx: usize,
+    // [state1_is_accept, ...]; only states of the original DFA can be accepting
+    pub accept_states: Vec<bool>,
+    // [state1_is_trap, ...]; a trap state cannot reach any accept state
+    pub trap_states: Vec<bool>,
+
+    // (state_idx * 256) + byte -> next_state_idx
+    pub transition_table: Vec<usize>,
+}
+
+/// Outgoing transitions of a state: (first byte, last byte, destination state), sorted.
+type ByteRow = Vec<(u8, u8, usize)>;
+
+impl Dfa {
+    /// Compiles the DFA into an equivalent [`ByteDfa`] that reads UTF-8 bytes instead of `char`s.
+    pub fn to_byte_dfa(&self) -> ByteDfa {
+        let char_state_count = self.state_keys.len();
+        let class_members = self.class_members();
+
+        let mut builder = Utf8Builder {
+            first_state_idx: char_state_count,
+            shared: HashMap::new(),
+            rows: Vec::new(),
+        };
+        let mut char_rows = Vec::with_capacity(char_state_count);
+
+        for src_idx in 0..char_state_count {
+            // contiguous character ranges of the alphabet, with their destination
+            let mut ranges: Vec<(u32, u32, usize)> = Vec::new();
+            for (class_idx, members) in class_members.iter().enumerate() {
+                let dest_idx = self.class_transition(src_idx, class_idx);
+                for &c in members {
+                    match ranges.last_mut() {
+                        Some((_, end, dest)) if *end + 1 == c as u32 && *dest == dest_idx => {
+                            *end = c as u32;
+                        }
+                        _ => ranges.push((c as u32, c as u32, dest_idx)),
+                    }
+                }
+            }
+            ranges.sort();
+
+            let mut sequences: Vec<(Vec<(u8, u8)>, usize)> = Vec::new();
+            for (start, end, dest_idx) in ranges {
+                for sequence in utf8_sequences(start, end) {
+                    sequences.push((sequence, dest_idx));
+                }
+            }
+            char_rows.push(builder.compile(&sequences, 0));
+        }
+
+        let dead_state_idx = char_state_count + builder.rows.len();
+        let state_count = dead_state_idx + 1;
+
+        let mut transition_table = vec![dead_state_idx; state_count * 256];
+        for (state_idx, row) in char_rows.iter().chain(&builder.rows).enumerate() {
+            for &(first, last, dest_idx) in row {
+                for byte in first..=last {
+                    transition_table[(state_idx * 256) + byte as usize] = dest_idx;
+                }
+            }
+        }
+
+        let mut accept_states = self.accept_states.clone();
+        accept_states.resize(state_count, false);
+
+        let mut dfa = ByteDfa {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            char_state_count,
+            start_state_idx: self.start_state_idx,
+            accept_states,
+            trap_states: Vec::new(),
+            transition_table,
+        };
+        dfa.trap_states = dfa.find_trap_states();
+        dfa
+    }
+}
+
+impl ByteDfa {
+    /// Runs the DFA on UTF-8 bytes and returns true if they are valid UTF-8 and the decoded string
+    /// is accepted, false otherwise.
+    pub fn run(&self, input: &[u8]) -> bool {
+        let mut current_state = self.start_state_idx;
+        for &byte in input {
+            current_state = self.transition_table[(current_state * 256) + byte as usize];
+            if self.trap_states[current_state] {
+                return false;
+            }
+        }
+        self.accept_states[current_state]
+    }
+
+    /// Returns the state reached from `state_idx` on `byte`.
+    pub fn next_state(&self, state_idx: usize, byte: u8) -> usize {
+        self.transition_table[(state_idx * 256) + byte as usize]
+    }
+
+    /// Returns the number of states, including the intermediate and dead states.
+    pub fn state_count(&self) -> usize {
+        self.accept_states.len()
+    }
+
+    /// Returns, for each state, whether no accept state can be reached from it.
+    fn find_trap_states(&self) -> Vec<bool> {
+        analysis::coreachable(&self.accept_states, |src| {
+            self.transition_table[src * 256..(src + 1) * 256]
+                .iter()
+                .cloned()
+        })
+        .iter()
+        .map(|&c| !c)
+        .collect()
+    }
+}
+
+/// Builds the intermediate states of a [`ByteDfa`], sharing identical states.
+struct Utf8Builder {
+    /// Index of the first intermediate state.
+    first_state_idx: usize,
+    /// row -> index of the intermediate state with exactly these transitions
+    shared: HashMap<ByteRow, usize>,
+    /// rows of the intermediate states, in index order
+    rows: Vec<ByteRow>,
+}
+
+impl Utf8Builder {
+    /// Returns the transitions of a state reading byte `depth` of the given sorted byte range
+    /// sequences, each leading to its destination once fully read.
+    ///
+    /// Sequences sharing the byte range at `depth` continue in a common intermediate state. Since
+    /// intermediate states are reused whenever their transitions are identical, the common
+    /// suffixes of different sequences (e.g. the final continuation bytes) are only built once.
+    fn compile(&mut self, sequences: &[(Vec<(u8, u8)>, usize)], depth: usize) -> ByteRow {
+        let mut row = ByteRow::new();
+        let mut group_start = 0;
+        while group_start < sequences.len() {
+            let range = sequences[group_start].0[depth];
+            let group_end = group_start
+                + sequences[group_start..]
+                    .iter()
+                    .take_while(|(sequence, _)| sequence[depth] == range)
+                    .count();
+            let group = &sequences[group_start..group_end];
+
+            let dest_idx = if group[0].0.len() == depth + 1 {
+                group[0].1
+            } else {
+                let next_row = self.compile(group, depth + 1);
+                self.intern(next_row)
+            };
+            row.push((range.0, range.1, dest_idx));
+            group_start = group_end;
+        }
+        row
+    }
+
+    /// Returns the index of the intermediate state with the given transitions, creating it if
+    /// needed.
+    fn intern(&mut self, row: ByteRow) -> usize {
+        if let Some(&idx) = self.shared.get(&row) {
+            return idx;
+        }
+        let idx = self.first_state_idx + self.rows.len();
+        self.shared.insert(row.clone(), idx);
+        self.rows.push(row);
+        idx
+    }
+}
+
+/// Splits the code points `start..=end` into sequences of byte ranges, such that the UTF-8
+/// encodings of the range are exactly the byte strings matching one of the sequences. Surrogate
+/// code points are skipped. The sequences are returned in increasing order.
+fn utf8_sequences(start: u32, end: u32) -> Vec<Vec<(u8, u8)>> {
+    let mut sequences = Vec::new();
+    // ranges are pushed high part first, so that the low part is handled first
+    let mut stack = vec![(start, end)];
+
+    'ranges: while let Some((start, end)) = stack.pop() {
+        if start <= 0xDFFF && end >= 0xD800 {
+            if end > 0xDFFF {
+                stack.push((0xE000, end));
+            }
+            if start < 0xD800 {
+                stack.push((start, 0xD7FF));
+            }
+            continue;
+        }
+
+        // every code point of a range must have the same encoded length
+        for max in [0x7F, 0x7FF, 0xFFFF] {
+            if start <= max && max < end {
+                stack.push((max + 1, end));
+                stack.push((start, max));
+                continue 'ranges;
+            }
+        }
+
+        // split until every continuation byte either spans its full range or shares its prefix
+        for i in 1..4 {
+            let mask = (1 << (6 * i)) - 1;
+            if start & !mask != end & !mask {
+                if start & mask != 0 {
+                    stack.push(((start | mask) + 1, end));
+                    stack.push((start, start | mask));
+                    continue 'ranges;
+                }
+                if end & mask != mask {
+                    stack.push((end & !mask, end));
+                    stack.push((start, (end & !mask) - 1));
+                    continue 'ranges;
+                }
+            }
+        }
+
+        let mut start_bytes = [0; 4];
+        let mut end_bytes = [0; 4];
+        let start_bytes = encode(start, &mut start_bytes);
+        let end_bytes = encode(end, &mut end_bytes);
+        sequences.push(
+            start_bytes
+                .iter()
+                .cloned()
+                .zip(end_bytes.iter().cloned())
+                .collect(),
+        );
+    }
+
+    sequences
+}
+
+/// Encodes a (non-surrogate) code point as UTF-8.
+fn encode(code_point: u32, buf: &mut [u8; 4]) -> &[u8] {
+    char::from_u32(code_point)
+        .expect("surrogates are split off")
+        .encode_utf8(buf)
+        .as_bytes()
+}